@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE users
+    DROP COLUMN IF EXISTS totp_last_used_step,
+    DROP COLUMN IF EXISTS totp_pending_secret,
+    DROP COLUMN IF EXISTS totp_secret,
+    DROP COLUMN IF EXISTS mfa_enabled;
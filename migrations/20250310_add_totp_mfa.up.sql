@@ -0,0 +1,6 @@
+-- Add up migration script here
+ALTER TABLE users
+    ADD COLUMN mfa_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN totp_secret VARCHAR(64),
+    ADD COLUMN totp_pending_secret VARCHAR(64),
+    ADD COLUMN totp_last_used_step BIGINT;
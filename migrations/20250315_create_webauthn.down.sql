@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS "webauthn_challenges";
+
+DROP TABLE IF EXISTS "webauthn_credentials";
+
+DROP TYPE IF EXISTS webauthn_ceremony;
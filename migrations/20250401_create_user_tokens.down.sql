@@ -0,0 +1,24 @@
+-- Add down migration script here
+ALTER TABLE users
+    ADD COLUMN verification_token VARCHAR(255),
+    ADD COLUMN token_expires_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE "magic_link_tokens" (
+    id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX magic_link_tokens_user_id_idx ON magic_link_tokens (user_id);
+
+-- 只有登录链接令牌可以还原，邮箱验证和密码重置令牌只保存了哈希，需要用户重新申请
+INSERT INTO magic_link_tokens (user_id, token_hash, expires_at, used_at, created_at)
+SELECT user_id, token_hash, expires_at, used_at, created_at
+FROM user_tokens
+WHERE purpose = 'magic_login';
+
+DROP TABLE IF EXISTS "user_tokens";
+DROP TYPE IF EXISTS user_token_purpose;
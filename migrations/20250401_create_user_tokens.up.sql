@@ -0,0 +1,41 @@
+-- Add up migration script here
+CREATE TYPE user_token_purpose AS ENUM (
+    'verify_email',
+    'reset_password',
+    'change_email',
+    'magic_login',
+    'invite'
+);
+
+CREATE TABLE "user_tokens" (
+    id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose user_token_purpose NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX user_tokens_user_id_purpose_idx ON user_tokens (user_id, purpose);
+
+-- 未过期的旧令牌迁移到新表：未验证用户的令牌视为邮箱验证令牌，已验证用户的令牌视为密码重置令牌
+INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at)
+SELECT id,
+       CASE WHEN verified THEN 'reset_password'::user_token_purpose
+            ELSE 'verify_email'::user_token_purpose END,
+       encode(sha256(convert_to(verification_token, 'UTF8')), 'hex'),
+       token_expires_at
+FROM users
+WHERE verification_token IS NOT NULL AND token_expires_at > NOW();
+
+INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at, used_at, created_at)
+SELECT user_id, 'magic_login', token_hash, expires_at, used_at, created_at
+FROM magic_link_tokens
+WHERE expires_at > NOW();
+
+DROP TABLE "magic_link_tokens";
+
+ALTER TABLE users
+    DROP COLUMN verification_token,
+    DROP COLUMN token_expires_at;
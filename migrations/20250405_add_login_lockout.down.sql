@@ -0,0 +1,20 @@
+-- Add down migration script here
+DELETE FROM user_tokens WHERE purpose = 'unlock_account';
+
+ALTER TYPE user_token_purpose RENAME TO user_token_purpose_old;
+CREATE TYPE user_token_purpose AS ENUM (
+    'verify_email',
+    'reset_password',
+    'change_email',
+    'magic_login',
+    'invite'
+);
+ALTER TABLE user_tokens
+    ALTER COLUMN purpose TYPE user_token_purpose USING purpose::text::user_token_purpose;
+DROP TYPE user_token_purpose_old;
+
+DROP TABLE IF EXISTS "login_attempts";
+
+ALTER TABLE users
+    DROP COLUMN failed_login_attempts,
+    DROP COLUMN locked_until;
@@ -0,0 +1,19 @@
+-- Add up migration script here
+ALTER TABLE users
+    ADD COLUMN failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN locked_until TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE "login_attempts" (
+    id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(45) NOT NULL,
+    success BOOLEAN NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX login_attempts_ip_address_created_at_idx ON login_attempts (ip_address, created_at);
+CREATE INDEX login_attempts_user_id_idx ON login_attempts (user_id);
+CREATE INDEX login_attempts_created_at_idx ON login_attempts (created_at);
+
+ALTER TYPE user_token_purpose ADD VALUE 'unlock_account';
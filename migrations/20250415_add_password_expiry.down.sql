@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE users
+    DROP COLUMN password_changed_at,
+    DROP COLUMN must_change_password;
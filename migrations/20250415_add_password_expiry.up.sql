@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE users
+    ADD COLUMN password_changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- 带 pepper 标识或更大 argon2 参数的哈希会超过 100 个字符
+ALTER TABLE users ALTER COLUMN password TYPE VARCHAR(255);
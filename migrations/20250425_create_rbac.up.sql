@@ -0,0 +1,47 @@
+-- Add up migration script here
+CREATE TABLE roles (
+    name VARCHAR(50) NOT NULL PRIMARY KEY,
+    description TEXT NOT NULL DEFAULT '',
+    -- 内置角色不能删除
+    is_system BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE permissions (
+    name VARCHAR(100) NOT NULL PRIMARY KEY,
+    description TEXT NOT NULL DEFAULT ''
+);
+
+CREATE TABLE role_permissions (
+    role_name VARCHAR(50) NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    permission VARCHAR(100) NOT NULL REFERENCES permissions(name) ON DELETE CASCADE,
+    PRIMARY KEY (role_name, permission)
+);
+
+INSERT INTO permissions (name, description) VALUES
+    ('account:manage', 'Manage own profile, password, MFA, passkeys and API keys'),
+    ('users:read', 'List users'),
+    ('users:write', 'Unlock users and force password changes'),
+    ('users:write_role', 'Change user roles'),
+    ('roles:read', 'List roles and permissions'),
+    ('roles:write', 'Create, update and delete roles');
+
+-- 将原有的枚举值映射为内置角色
+INSERT INTO roles (name, description, is_system) VALUES
+    ('admin', 'Administrator with all permissions', TRUE),
+    ('user', 'Regular user', TRUE);
+
+INSERT INTO role_permissions (role_name, permission)
+SELECT 'admin', name FROM permissions;
+
+INSERT INTO role_permissions (role_name, permission) VALUES ('user', 'account:manage');
+
+ALTER TABLE users ALTER COLUMN role DROP DEFAULT;
+ALTER TABLE users ALTER COLUMN role TYPE VARCHAR(50) USING role::TEXT;
+ALTER TABLE users ALTER COLUMN role SET DEFAULT 'user';
+ALTER TABLE users ADD CONSTRAINT users_role_fkey FOREIGN KEY (role) REFERENCES roles(name);
+
+CREATE INDEX users_role_idx ON users (role);
+
+DROP TYPE user_role;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS audit_events;
+
+ALTER TABLE users DROP COLUMN IF EXISTS disabled_at;
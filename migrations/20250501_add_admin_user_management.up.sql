@@ -0,0 +1,15 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN disabled_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE audit_events (
+    id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    -- 不设外键：用户删除后仍保留审计记录
+    actor_id UUID,
+    target_user_id UUID,
+    action VARCHAR(50) NOT NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX audit_events_target_user_id_created_at_idx ON audit_events (target_user_id, created_at);
+CREATE INDEX audit_events_actor_id_idx ON audit_events (actor_id);
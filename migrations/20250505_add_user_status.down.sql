@@ -0,0 +1,13 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS users_suspended_until_idx;
+
+ALTER TABLE users ADD COLUMN disabled_at TIMESTAMP WITH TIME ZONE;
+
+UPDATE users SET disabled_at = updated_at WHERE status <> 'active';
+
+ALTER TABLE users
+    DROP COLUMN IF EXISTS status,
+    DROP COLUMN IF EXISTS status_reason,
+    DROP COLUMN IF EXISTS suspended_until;
+
+DROP TYPE IF EXISTS user_status;
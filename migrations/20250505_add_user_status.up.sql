@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TYPE user_status AS ENUM ('active', 'suspended', 'banned', 'deactivated');
+
+ALTER TABLE users
+    ADD COLUMN status user_status NOT NULL DEFAULT 'active',
+    ADD COLUMN status_reason TEXT,
+    ADD COLUMN suspended_until TIMESTAMP WITH TIME ZONE;
+
+-- 已停用的账户迁移为无限期暂停
+UPDATE users SET status = 'suspended' WHERE disabled_at IS NOT NULL;
+
+ALTER TABLE users DROP COLUMN disabled_at;
+
+-- 后台任务按到期时间查找需要恢复的暂停账户
+CREATE INDEX users_suspended_until_idx ON users (suspended_until) WHERE status = 'suspended';
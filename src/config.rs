@@ -1,32 +1,160 @@
 use std::env;
 
+use crate::utils::password;
+
 // -- 应用配置结构体
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
     pub server_port: u16,
-    pub jwt_secret: String,
+    pub jwt: JwtConfig,
+    pub token_hmac_secret: String,
     pub jwt_maxage: i64,
+    pub refresh_token_maxage: i64,
+    pub login_max_attempts: i32,
+    pub login_lockout_minutes: i64,
+    pub login_ip_max_attempts: i64,
+    pub privacy_mode: bool,
+    pub trust_token_claims_on_read: bool,
+    pub permission_cache_ttl_seconds: u64,
+    pub account_deletion_grace_days: i64,
+    pub password_policy: PasswordPolicyConfig,
+    pub password_hash: PasswordHashConfig,
     pub frontend_url: String,
+    pub totp_issuer: String,
+    pub webauthn_rp_id: String,
+    pub webauthn_rp_origin: String,
+    pub webauthn_rp_name: String,
+    pub oauth_redirect_base_url: String,
+    pub oidc_providers: Vec<OidcProviderConfig>,
     pub log_dir: String,
     pub log_retention_days: u64,
 }
 
+// -- JWT 签名配置，配置私钥后使用非对称签名，否则使用 `JWT_SECRET_KEY` 进行 HS256 签名
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    /// HS256 密钥，只在未配置私钥时使用
+    pub secret: Option<String>,
+    /// 当前签名私钥（RSA 或 Ed25519，PEM 格式）
+    pub private_key_file: Option<String>,
+    /// 密钥轮换期间仍然接受的其他公钥（PEM 格式）
+    pub verification_key_files: Vec<String>,
+    /// 令牌的 `iss` 声明
+    pub issuer: String,
+    /// 令牌的 `aud` 声明
+    pub audience: String,
+}
+
+// -- OIDC 身份提供方配置
+#[derive(Debug, Clone)]
+pub struct OidcProviderConfig {
+    pub name: String,
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scopes: Vec<String>,
+}
+
+// -- 密码策略配置，注册、修改密码和重置密码时生效
+#[derive(Debug, Clone)]
+pub struct PasswordPolicyConfig {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// zxcvbn 强度评分下限（0-4）
+    pub min_strength_score: u8,
+    /// 新密码不能与最近使用过的这么多个密码（含当前密码）相同，0 表示不检查
+    pub history_size: usize,
+    /// 密码最长使用天数，超过后登录时必须先修改密码，0 表示不限制
+    pub max_age_days: i64,
+}
+
+// -- 密码哈希配置，修改 argon2 参数或 pepper 后旧哈希会在用户下次登录时自动升级
+#[derive(Debug, Clone)]
+pub struct PasswordHashConfig {
+    /// argon2 内存开销（KiB）
+    pub memory_kib: u32,
+    /// argon2 迭代次数
+    pub iterations: u32,
+    /// argon2 并行度
+    pub parallelism: u32,
+    /// 服务端密钥，作为 argon2 的 secret 参与哈希，只保存在配置中
+    pub pepper: Option<String>,
+}
+
 impl Config {
     /// 从环境变量加载配置
     ///
-    /// 读取环境变量 `DATABASE_URL`, `JWT_SECRET_KEY`, `JWT_MAXAGE`, `SERVER_PORT`,
-    /// `FRONTEND_URL`, `LOG_DIR` 和 `LOG_RETENTION_DAYS`，并将其加载到 `Config` 实例中。
+    /// 读取环境变量 `DATABASE_URL`, `JWT_*`, `TOKEN_HMAC_SECRET`,
+    /// `REFRESH_TOKEN_MAXAGE`, `LOGIN_MAX_ATTEMPTS`, `LOGIN_LOCKOUT_MINUTES`,
+    /// `LOGIN_IP_MAX_ATTEMPTS`, `PRIVACY_MODE`, `TRUST_TOKEN_CLAIMS_ON_READ`,
+    /// `PERMISSION_CACHE_TTL_SECONDS`, `ACCOUNT_DELETION_GRACE_DAYS`, `PASSWORD_*`,
+    /// `ARGON2_*`, `SERVER_PORT`,
+    /// `FRONTEND_URL`, `TOTP_ISSUER`, `WEBAUTHN_RP_ID`, `WEBAUTHN_RP_ORIGIN`, `WEBAUTHN_RP_NAME`,
+    /// `OAUTH_REDIRECT_BASE_URL`, `OIDC_PROVIDERS`, `LOG_DIR` 和 `LOG_RETENTION_DAYS`，
+    /// 并将其加载到 `Config` 实例中。
     /// 如果必要的环境变量不存在或解析失败，将会 panic。
     ///
     pub fn from_env() -> Self {
         let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        let jwt_secret = std::env::var("JWT_SECRET_KEY").expect("JWT_SECRET_KEY must be set");
+        let jwt = JwtConfig::from_env();
+        // 邮件令牌（邮箱验证、密码重置、登录链接）的 HMAC 密钥，更换后未使用的邮件链接全部失效
+        let token_hmac_secret =
+            env::var("TOKEN_HMAC_SECRET").expect("TOKEN_HMAC_SECRET must be set");
         let jwt_maxage = std::env::var("JWT_MAXAGE")
             .expect("JWT_MAXAGE must be set")
             .parse()
             .expect("JWT_MAXAGE must be a number");
 
+        // 刷新令牌有效期（分钟），默认为 7 天
+        let refresh_token_maxage = env::var("REFRESH_TOKEN_MAXAGE")
+            .unwrap_or_else(|_| "10080".to_string())
+            .parse()
+            .expect("REFRESH_TOKEN_MAXAGE must be a number");
+
+        // 连续密码错误达到该次数后临时锁定账户，默认为 5 次
+        let login_max_attempts = env::var("LOGIN_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse()
+            .expect("LOGIN_MAX_ATTEMPTS must be a number");
+
+        // 账户锁定时长（分钟），同时也是按 IP 统计失败次数的时间窗口，默认为 15 分钟
+        let login_lockout_minutes = env::var("LOGIN_LOCKOUT_MINUTES")
+            .unwrap_or_else(|_| "15".to_string())
+            .parse()
+            .expect("LOGIN_LOCKOUT_MINUTES must be a number");
+
+        // 单个 IP 在时间窗口内允许的失败登录次数（不区分账户），默认为 50 次
+        let login_ip_max_attempts = env::var("LOGIN_IP_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "50".to_string())
+            .parse()
+            .expect("LOGIN_IP_MAX_ATTEMPTS must be a number");
+
+        // 隐私模式：注册、忘记密码、重新发送验证邮件不再透露邮箱是否已注册，默认关闭
+        let privacy_mode = env_flag("PRIVACY_MODE");
+
+        // 个人资料等少数只读接口直接信任访问令牌中的声明，不再查询黑名单、令牌版本和用户，默认关闭
+        let trust_token_claims_on_read = env_flag("TRUST_TOKEN_CLAIMS_ON_READ");
+
+        // 角色权限的缓存时间（秒），通过角色接口修改后本实例立即生效，其他实例最多延迟该时间，默认为 60 秒
+        let permission_cache_ttl_seconds = env::var("PERMISSION_CACHE_TTL_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()
+            .expect("PERMISSION_CACHE_TTL_SECONDS must be a number");
+
+        // 用户申请删除账户后的宽限期（天），期间登录会恢复账户，过后彻底删除，默认为 30 天
+        let account_deletion_grace_days = env::var("ACCOUNT_DELETION_GRACE_DAYS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .expect("ACCOUNT_DELETION_GRACE_DAYS must be a number");
+
+        let password_policy = PasswordPolicyConfig::from_env();
+        let password_hash = PasswordHashConfig::from_env();
+
         let server_port = env::var("SERVER_PORT")
             .unwrap_or_else(|_| "3000".to_string())
             .parse()
@@ -35,6 +163,28 @@ impl Config {
         let frontend_url = env::var("FRONTEND_URL")
             .unwrap_or_else(|_| "http://localhost:5173".to_string());
             
+        // 身份验证器应用中显示的发行方名称
+        let totp_issuer = env::var("TOTP_ISSUER").unwrap_or_else(|_| "Axum Backend".to_string());
+
+        // WebAuthn 依赖方配置，RP ID 必须是前端页面所在域名（或其父域名）
+        let webauthn_rp_id = env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| "localhost".to_string());
+        let webauthn_rp_origin =
+            env::var("WEBAUTHN_RP_ORIGIN").unwrap_or_else(|_| frontend_url.clone());
+        let webauthn_rp_name = env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| totp_issuer.clone());
+
+        // OAuth 回调地址前缀，提供方回调地址为 `{前缀}/{提供方名称}/callback`
+        let oauth_redirect_base_url = env::var("OAUTH_REDIRECT_BASE_URL")
+            .unwrap_or_else(|_| format!("http://localhost:{}/api/auth/oauth", server_port));
+
+        // 逗号分隔的 OIDC 提供方名称，例如 `google,keycloak`
+        let oidc_providers = env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(OidcProviderConfig::from_env)
+            .collect();
+
         // 日志目录，默认为 /var/log/axum_backend
         let log_dir = env::var("LOG_DIR")
             .unwrap_or_else(|_| "/var/log/axum_backend".to_string());
@@ -46,13 +196,199 @@ impl Config {
             .unwrap_or(7);
 
         Self {
-            jwt_secret,
+            jwt,
+            token_hmac_secret,
             jwt_maxage,
+            refresh_token_maxage,
+            login_max_attempts,
+            login_lockout_minutes,
+            login_ip_max_attempts,
+            privacy_mode,
+            trust_token_claims_on_read,
+            permission_cache_ttl_seconds,
+            account_deletion_grace_days,
+            password_policy,
+            password_hash,
             database_url,
             server_port,
             frontend_url,
+            totp_issuer,
+            webauthn_rp_id,
+            webauthn_rp_origin,
+            webauthn_rp_name,
+            oauth_redirect_base_url,
+            oidc_providers,
             log_dir,
             log_retention_days,
         }
     }
 }
+
+impl JwtConfig {
+    /// 从环境变量加载 JWT 签名配置
+    ///
+    /// 读取环境变量 `JWT_SECRET_KEY`, `JWT_PRIVATE_KEY_FILE`, `JWT_VERIFICATION_KEY_FILES`
+    /// （逗号分隔的公钥文件路径）、`JWT_ISSUER`（默认 `axum-backend`）
+    /// 和 `JWT_AUDIENCE`（默认与 `JWT_ISSUER` 相同）。
+    /// 如果 `JWT_SECRET_KEY` 和 `JWT_PRIVATE_KEY_FILE` 都未设置，将会 panic。
+    ///
+    fn from_env() -> Self {
+        let secret = env::var("JWT_SECRET_KEY")
+            .ok()
+            .filter(|secret| !secret.is_empty());
+        let private_key_file = env::var("JWT_PRIVATE_KEY_FILE")
+            .ok()
+            .filter(|path| !path.is_empty());
+        if secret.is_none() && private_key_file.is_none() {
+            panic!("JWT_SECRET_KEY or JWT_PRIVATE_KEY_FILE must be set");
+        }
+
+        let verification_key_files = env::var("JWT_VERIFICATION_KEY_FILES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        let issuer = env::var("JWT_ISSUER").unwrap_or_else(|_| "axum-backend".to_string());
+        let audience = env::var("JWT_AUDIENCE").unwrap_or_else(|_| issuer.clone());
+
+        JwtConfig {
+            secret,
+            private_key_file,
+            verification_key_files,
+            issuer,
+            audience,
+        }
+    }
+}
+
+impl PasswordPolicyConfig {
+    /// 从环境变量加载密码策略
+    ///
+    /// 读取环境变量 `PASSWORD_MIN_LENGTH`（默认 8）、`PASSWORD_MAX_LENGTH`（默认且最大为 64）、
+    /// `PASSWORD_REQUIRE_LOWERCASE`, `PASSWORD_REQUIRE_UPPERCASE`, `PASSWORD_REQUIRE_DIGIT`,
+    /// `PASSWORD_REQUIRE_SYMBOL`（默认均不要求）、`PASSWORD_MIN_SCORE`（默认 3）
+    /// `PASSWORD_HISTORY_SIZE`（默认 5）和 `PASSWORD_MAX_AGE_DAYS`（默认 0，不限制）。
+    /// 如果环境变量解析失败，将会 panic。
+    ///
+    fn from_env() -> Self {
+        let min_length = env::var("PASSWORD_MIN_LENGTH")
+            .unwrap_or_else(|_| "8".to_string())
+            .parse()
+            .expect("PASSWORD_MIN_LENGTH must be a number");
+
+        // 密码哈希最多接受 64 字节，更大的值会被截断为 64
+        let max_length = env::var("PASSWORD_MAX_LENGTH")
+            .unwrap_or_else(|_| "64".to_string())
+            .parse::<usize>()
+            .expect("PASSWORD_MAX_LENGTH must be a number")
+            .min(password::MAX_PASSWORD_LENGTH);
+
+        let min_strength_score = env::var("PASSWORD_MIN_SCORE")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse::<u8>()
+            .expect("PASSWORD_MIN_SCORE must be a number between 0 and 4")
+            .min(4);
+
+        let history_size = env::var("PASSWORD_HISTORY_SIZE")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse()
+            .expect("PASSWORD_HISTORY_SIZE must be a number");
+
+        let max_age_days = env::var("PASSWORD_MAX_AGE_DAYS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse()
+            .expect("PASSWORD_MAX_AGE_DAYS must be a number");
+
+        PasswordPolicyConfig {
+            min_length,
+            max_length,
+            require_lowercase: env_flag("PASSWORD_REQUIRE_LOWERCASE"),
+            require_uppercase: env_flag("PASSWORD_REQUIRE_UPPERCASE"),
+            require_digit: env_flag("PASSWORD_REQUIRE_DIGIT"),
+            require_symbol: env_flag("PASSWORD_REQUIRE_SYMBOL"),
+            min_strength_score,
+            history_size,
+            max_age_days,
+        }
+    }
+}
+
+impl PasswordHashConfig {
+    /// 从环境变量加载密码哈希配置
+    ///
+    /// 读取环境变量 `ARGON2_MEMORY_KIB`（默认 19456）、`ARGON2_ITERATIONS`（默认 2）、
+    /// `ARGON2_PARALLELISM`（默认 1）和可选的 `PASSWORD_PEPPER`。
+    /// 如果环境变量解析失败或参数无效，将会 panic。
+    ///
+    fn from_env() -> Self {
+        let memory_kib = env::var("ARGON2_MEMORY_KIB")
+            .unwrap_or_else(|_| "19456".to_string())
+            .parse()
+            .expect("ARGON2_MEMORY_KIB must be a number");
+        let iterations = env::var("ARGON2_ITERATIONS")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse()
+            .expect("ARGON2_ITERATIONS must be a number");
+        let parallelism = env::var("ARGON2_PARALLELISM")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse()
+            .expect("ARGON2_PARALLELISM must be a number");
+        // 更换 pepper 后使用旧 pepper 的密码将无法校验，只能通过重置密码恢复
+        let pepper = env::var("PASSWORD_PEPPER")
+            .ok()
+            .filter(|pepper| !pepper.is_empty());
+
+        let config = PasswordHashConfig {
+            memory_kib,
+            iterations,
+            parallelism,
+            pepper,
+        };
+        password::validate_config(&config).expect("ARGON2_* parameters are invalid");
+
+        config
+    }
+}
+
+impl OidcProviderConfig {
+    /// 从环境变量加载单个 OIDC 提供方配置
+    ///
+    /// 读取环境变量 `OIDC_{NAME}_ISSUER`, `OIDC_{NAME}_CLIENT_ID`, `OIDC_{NAME}_CLIENT_SECRET`
+    /// 和 `OIDC_{NAME}_SCOPES`，其中 `{NAME}` 为大写的提供方名称。
+    /// 如果必要的环境变量不存在，将会 panic。
+    ///
+    fn from_env(name: &str) -> Self {
+        let prefix = format!("OIDC_{}", name.to_uppercase().replace('-', "_"));
+
+        let issuer_url = env::var(format!("{}_ISSUER", prefix))
+            .unwrap_or_else(|_| panic!("{}_ISSUER must be set", prefix));
+        let client_id = env::var(format!("{}_CLIENT_ID", prefix))
+            .unwrap_or_else(|_| panic!("{}_CLIENT_ID must be set", prefix));
+        // 公共客户端可以不配置密钥，仅依赖 PKCE
+        let client_secret = env::var(format!("{}_CLIENT_SECRET", prefix)).ok();
+        let scopes = env::var(format!("{}_SCOPES", prefix))
+            .unwrap_or_else(|_| "openid email profile".to_string())
+            .split([' ', ','])
+            .filter(|scope| !scope.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        OidcProviderConfig {
+            name: name.to_lowercase(),
+            issuer_url,
+            client_id,
+            client_secret,
+            scopes,
+        }
+    }
+}
+
+/// 读取布尔型环境变量，`1`、`true` 或 `yes` 视为开启，未设置时为关闭
+fn env_flag(name: &str) -> bool {
+    env::var(name)
+        .map(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
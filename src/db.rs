@@ -2,9 +2,27 @@ use sqlx::postgres::{PgPool, PgPoolOptions, Postgres};
 use sqlx::Pool;
 use std::time::Duration;
 
+mod api_key;
+mod audit_event;
+mod data_export;
+mod identity;
+mod login_attempt;
+mod recovery_code;
+mod role;
+mod session;
 mod user;
+mod webauthn;
 
-pub use user::UserExt;
+pub use api_key::ApiKeyExt;
+pub use audit_event::AuditEventExt;
+pub use data_export::DataExportExt;
+pub use identity::IdentityExt;
+pub use login_attempt::LoginAttemptExt;
+pub use recovery_code::RecoveryCodeExt;
+pub use role::RoleExt;
+pub use session::SessionExt;
+pub use user::{UserExt, UserUpdate};
+pub use webauthn::WebauthnExt;
 
 /// 数据库客户端结构体 -- 封装了数据库连接池
 #[derive(Debug, Clone)]
@@ -41,4 +59,10 @@ pub enum DbError {
 
     #[error("Token expired or invalid")]
     InvalidToken,
+
+    #[error("Role not found")]
+    RoleNotFound,
+
+    #[error("Cannot remove the last active admin")]
+    LastAdmin,
 }
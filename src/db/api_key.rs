@@ -0,0 +1,133 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Error;
+use uuid::Uuid;
+
+use super::DBClient;
+use crate::models::ApiKey;
+
+/// API 密钥数据库操作扩展特征 -- 定义了 API 密钥的签发、查询、吊销与使用记录
+#[async_trait]
+pub trait ApiKeyExt {
+    /// 保存 API 密钥
+    ///
+    /// # 参数
+    /// - `user_id` -- 用户ID
+    /// - `name` -- 密钥名称
+    /// - `prefix` -- 密钥开头的若干字符
+    /// - `key_hash` -- 密钥的 SHA-256 哈希
+    /// - `scopes` -- 授权范围
+    /// - `expires_at` -- 密钥过期时间
+    async fn save_api_key(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &[String],
+        expires_at: DateTime<Utc>,
+    ) -> Result<ApiKey, Error>;
+
+    /// 获取用户的全部 API 密钥（包括已吊销和已过期的）
+    async fn get_api_keys(&self, user_id: Uuid) -> Result<Vec<ApiKey>, Error>;
+
+    /// 根据密钥哈希查找有效的 API 密钥 -- 已吊销或已过期的密钥不会返回
+    async fn get_active_api_key(&self, key_hash: &str) -> Result<Option<ApiKey>, Error>;
+
+    /// 吊销用户的 API 密钥
+    ///
+    /// # 返回
+    /// - `Ok(true)` -- 吊销成功
+    /// - `Ok(false)` -- 密钥不存在、不属于该用户或已被吊销
+    async fn revoke_api_key(&self, user_id: Uuid, id: Uuid) -> Result<bool, Error>;
+
+    /// 记录 API 密钥的使用时间 -- 一分钟内只更新一次，避免每个请求都写库
+    async fn touch_api_key(&self, id: Uuid) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl ApiKeyExt for DBClient {
+    async fn save_api_key(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &[String],
+        expires_at: DateTime<Utc>,
+    ) -> Result<ApiKey, Error> {
+        let api_key = sqlx::query_as!(
+            ApiKey,
+            r#"
+            INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, user_id, name, prefix, key_hash, scopes, expires_at, last_used_at, revoked_at, created_at
+            "#,
+            user_id,
+            name,
+            prefix,
+            key_hash,
+            scopes,
+            expires_at
+        )
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(api_key)
+    }
+
+    async fn get_api_keys(&self, user_id: Uuid) -> Result<Vec<ApiKey>, Error> {
+        let api_keys = sqlx::query_as!(
+            ApiKey,
+            r#"SELECT id, user_id, name, prefix, key_hash, scopes, expires_at, last_used_at, revoked_at, created_at FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC"#,
+            user_id
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(api_keys)
+    }
+
+    async fn get_active_api_key(&self, key_hash: &str) -> Result<Option<ApiKey>, Error> {
+        let api_key = sqlx::query_as!(
+            ApiKey,
+            r#"SELECT id, user_id, name, prefix, key_hash, scopes, expires_at, last_used_at, revoked_at, created_at FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL AND expires_at > Now()"#,
+            key_hash
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(api_key)
+    }
+
+    async fn revoke_api_key(&self, user_id: Uuid, id: Uuid) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE api_keys
+            SET revoked_at = Now()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            "#,
+            id,
+            user_id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn touch_api_key(&self, id: Uuid) -> Result<(), Error> {
+        let _ = sqlx::query!(
+            r#"
+            UPDATE api_keys
+            SET last_used_at = Now()
+            WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < Now() - INTERVAL '1 minute')
+            "#,
+            id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+}
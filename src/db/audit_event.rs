@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use sqlx::Error;
+use uuid::Uuid;
+
+use super::DBClient;
+use crate::models::{AuditAction, AuditEvent};
+
+/// 审计事件数据库操作扩展特征 -- 记录管理员对账户的操作
+#[async_trait]
+pub trait AuditEventExt {
+    /// 记录审计事件
+    ///
+    /// # 参数
+    /// - `actor_id` -- 操作者ID
+    /// - `target_user_id` -- 被操作的用户ID
+    /// - `action` -- 事件类型
+    /// - `details` -- 事件详情，例如修改前后的字段
+    async fn record_audit_event(
+        &self,
+        actor_id: Option<Uuid>,
+        target_user_id: Option<Uuid>,
+        action: AuditAction,
+        details: serde_json::Value,
+    ) -> Result<(), Error>;
+
+    /// 获取针对该用户的审计事件，按时间倒序 -- 不包含用户对其他人执行的操作，避免泄露他人的个人数据
+    async fn get_user_audit_events(&self, user_id: Uuid) -> Result<Vec<AuditEvent>, Error>;
+}
+
+#[async_trait]
+impl AuditEventExt for DBClient {
+    async fn record_audit_event(
+        &self,
+        actor_id: Option<Uuid>,
+        target_user_id: Option<Uuid>,
+        action: AuditAction,
+        details: serde_json::Value,
+    ) -> Result<(), Error> {
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO audit_events (actor_id, target_user_id, action, details)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            actor_id,
+            target_user_id,
+            action.to_str(),
+            details
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_user_audit_events(&self, user_id: Uuid) -> Result<Vec<AuditEvent>, Error> {
+        let events = sqlx::query_as!(
+            AuditEvent,
+            r#"
+            SELECT id, actor_id, target_user_id, action, details, created_at
+            FROM audit_events
+            WHERE target_user_id = $1
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,112 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Error;
+use uuid::Uuid;
+
+use super::DBClient;
+use crate::models::DataExport;
+
+/// 个人数据导出数据库操作扩展特征 -- 定义了导出任务的创建、完成、下载与过期清理
+#[async_trait]
+pub trait DataExportExt {
+    /// 创建导出任务
+    ///
+    /// # 参数
+    /// - `user_id` -- 用户ID
+    /// - `token_hash` -- 下载令牌的 HMAC
+    /// - `expires_at` -- 下载链接过期时间
+    async fn save_data_export(
+        &self,
+        user_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<DataExport, Error>;
+
+    /// 写入导出内容并标记为已完成
+    async fn complete_data_export(&self, id: Uuid, data: serde_json::Value) -> Result<(), Error>;
+
+    /// 删除导出任务 -- 生成失败时调用
+    async fn delete_data_export(&self, id: Uuid) -> Result<(), Error>;
+
+    /// 根据下载令牌哈希查找已完成且未过期的导出
+    async fn get_data_export_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<DataExport>, Error>;
+
+    /// 删除已过期的导出（包括未能完成的任务），返回删除的数量
+    async fn delete_expired_data_exports(&self) -> Result<u64, Error>;
+}
+
+#[async_trait]
+impl DataExportExt for DBClient {
+    async fn save_data_export(
+        &self,
+        user_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<DataExport, Error> {
+        let data_export = sqlx::query_as!(
+            DataExport,
+            r#"
+            INSERT INTO data_exports (user_id, token_hash, expires_at)
+            VALUES ($1, $2, $3)
+            RETURNING id, user_id, token_hash, data, completed_at, expires_at, created_at
+            "#,
+            user_id,
+            token_hash,
+            expires_at
+        )
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(data_export)
+    }
+
+    async fn complete_data_export(&self, id: Uuid, data: serde_json::Value) -> Result<(), Error> {
+        let _ = sqlx::query!(
+            r#"UPDATE data_exports SET data = $2, completed_at = Now() WHERE id = $1"#,
+            id,
+            data
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_data_export(&self, id: Uuid) -> Result<(), Error> {
+        let _ = sqlx::query!(r#"DELETE FROM data_exports WHERE id = $1"#, id)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_data_export_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<DataExport>, Error> {
+        let data_export = sqlx::query_as!(
+            DataExport,
+            r#"
+            SELECT id, user_id, token_hash, data, completed_at, expires_at, created_at
+            FROM data_exports
+            WHERE token_hash = $1 AND completed_at IS NOT NULL AND expires_at > Now()
+            "#,
+            token_hash
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(data_export)
+    }
+
+    async fn delete_expired_data_exports(&self) -> Result<u64, Error> {
+        let result = sqlx::query!(r#"DELETE FROM data_exports WHERE expires_at <= Now()"#)
+            .execute(self.pool())
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
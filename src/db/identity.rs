@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Error;
+use uuid::Uuid;
+
+use super::DBClient;
+use crate::models::{OAuthState, User, UserIdentity, UserStatus};
+
+/// 第三方身份数据库操作扩展特征 -- 定义了 OAuth 授权状态与第三方身份关联的存储
+#[async_trait]
+pub trait IdentityExt {
+    /// 保存授权状态 -- 同时清理已过期的授权状态
+    ///
+    /// # 参数
+    /// - `state_hash` -- `state` 参数的 SHA-256 哈希
+    /// - `provider` -- 身份提供方名称
+    /// - `pkce_verifier` -- PKCE 校验码
+    /// - `nonce` -- ID 令牌中需要回传的随机值
+    /// - `expires_at` -- 授权状态过期时间
+    async fn save_oauth_state(
+        &self,
+        state_hash: &str,
+        provider: &str,
+        pkce_verifier: &str,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), Error>;
+
+    /// 取出授权状态 -- 取出即删除，保证每个 `state` 只能使用一次
+    async fn take_oauth_state(&self, state_hash: &str) -> Result<Option<OAuthState>, Error>;
+
+    /// 根据提供方与用户标识查找第三方身份
+    async fn get_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<UserIdentity>, Error>;
+
+    /// 将第三方身份关联到已有用户
+    async fn link_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) -> Result<UserIdentity, Error>;
+
+    /// 记录第三方身份的登录时间
+    async fn touch_identity(&self, identity_id: Uuid) -> Result<(), Error>;
+
+    /// 获取用户关联的全部第三方身份
+    async fn get_user_identities(&self, user_id: Uuid) -> Result<Vec<UserIdentity>, Error>;
+
+    /// 使用第三方身份创建新用户 -- 邮箱已由提供方验证，用户直接标记为已验证
+    ///
+    /// # 参数
+    /// - `password` -- 随机密码的哈希，用户可通过忘记密码流程设置本地密码
+    async fn save_identity_user(
+        &self,
+        name: &str,
+        email: &str,
+        password: &str,
+        provider: &str,
+        subject: &str,
+    ) -> Result<User, Error>;
+}
+
+#[async_trait]
+impl IdentityExt for DBClient {
+    async fn save_oauth_state(
+        &self,
+        state_hash: &str,
+        provider: &str,
+        pkce_verifier: &str,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let _ = sqlx::query!(r#"DELETE FROM oauth_states WHERE expires_at < Now()"#)
+            .execute(self.pool())
+            .await?;
+
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO oauth_states (state_hash, provider, pkce_verifier, nonce, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            state_hash,
+            provider,
+            pkce_verifier,
+            nonce,
+            expires_at
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn take_oauth_state(&self, state_hash: &str) -> Result<Option<OAuthState>, Error> {
+        let state = sqlx::query_as!(
+            OAuthState,
+            r#"
+            DELETE FROM oauth_states
+            WHERE state_hash = $1
+            RETURNING state_hash, provider, pkce_verifier, nonce, expires_at, created_at
+            "#,
+            state_hash
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(state)
+    }
+
+    async fn get_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<UserIdentity>, Error> {
+        let identity = sqlx::query_as!(
+            UserIdentity,
+            r#"SELECT id, user_id, provider, subject, email, last_login_at, created_at FROM user_identities WHERE provider = $1 AND subject = $2"#,
+            provider,
+            subject
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(identity)
+    }
+
+    async fn link_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) -> Result<UserIdentity, Error> {
+        let identity = sqlx::query_as!(
+            UserIdentity,
+            r#"
+            INSERT INTO user_identities (user_id, provider, subject, email, last_login_at)
+            VALUES ($1, $2, $3, $4, Now())
+            RETURNING id, user_id, provider, subject, email, last_login_at, created_at
+            "#,
+            user_id,
+            provider,
+            subject,
+            email
+        )
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(identity)
+    }
+
+    async fn get_user_identities(&self, user_id: Uuid) -> Result<Vec<UserIdentity>, Error> {
+        let identities = sqlx::query_as!(
+            UserIdentity,
+            r#"SELECT id, user_id, provider, subject, email, last_login_at, created_at FROM user_identities WHERE user_id = $1 ORDER BY created_at"#,
+            user_id
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(identities)
+    }
+
+    async fn touch_identity(&self, identity_id: Uuid) -> Result<(), Error> {
+        let _ = sqlx::query!(
+            r#"UPDATE user_identities SET last_login_at = Now() WHERE id = $1"#,
+            identity_id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn save_identity_user(
+        &self,
+        name: &str,
+        email: &str,
+        password: &str,
+        provider: &str,
+        subject: &str,
+    ) -> Result<User, Error> {
+        let mut tx = self.pool().begin().await?;
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            INSERT INTO users (name, email, password, verified)
+            VALUES ($1, $2, $3, true)
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
+            "#,
+            name,
+            email,
+            password
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO user_identities (user_id, provider, subject, email, last_login_at)
+            VALUES ($1, $2, $3, $4, Now())
+            "#,
+            user.id,
+            provider,
+            subject,
+            email
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+}
@@ -0,0 +1,103 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Error;
+use uuid::Uuid;
+
+use super::DBClient;
+use crate::models::LoginAttempt;
+
+/// 登录尝试记录保留时间（小时）
+const LOGIN_ATTEMPT_RETENTION_HOURS: i32 = 24;
+
+/// 登录尝试数据库操作扩展特征 -- 记录每次密码登录的结果，用于按来源 IP 限制暴力破解
+#[async_trait]
+pub trait LoginAttemptExt {
+    /// 记录一次登录尝试 -- 同时清理超过保留时间的记录
+    ///
+    /// # 参数
+    /// - `user_id` -- 用户ID，邮箱未注册时为 `None`
+    /// - `email` -- 登录时提交的邮箱
+    /// - `ip_address` -- 来源 IP
+    /// - `success` -- 是否登录成功
+    async fn record_login_attempt(
+        &self,
+        user_id: Option<Uuid>,
+        email: &str,
+        ip_address: &str,
+        success: bool,
+    ) -> Result<(), Error>;
+
+    /// 统计来源 IP 在指定时间之后的失败登录次数
+    async fn count_failed_login_attempts_by_ip(
+        &self,
+        ip_address: &str,
+        since: DateTime<Utc>,
+    ) -> Result<i64, Error>;
+
+    /// 获取用户的登录记录（保留时间内），按时间倒序
+    async fn get_login_attempts(&self, user_id: Uuid) -> Result<Vec<LoginAttempt>, Error>;
+}
+
+#[async_trait]
+impl LoginAttemptExt for DBClient {
+    async fn record_login_attempt(
+        &self,
+        user_id: Option<Uuid>,
+        email: &str,
+        ip_address: &str,
+        success: bool,
+    ) -> Result<(), Error> {
+        let _ = sqlx::query!(
+            r#"DELETE FROM login_attempts WHERE created_at < Now() - make_interval(hours => $1)"#,
+            LOGIN_ATTEMPT_RETENTION_HOURS
+        )
+        .execute(self.pool())
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO login_attempts (user_id, email, ip_address, success)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            user_id,
+            email,
+            ip_address,
+            success
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn count_failed_login_attempts_by_ip(
+        &self,
+        ip_address: &str,
+        since: DateTime<Utc>,
+    ) -> Result<i64, Error> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) FROM login_attempts
+            WHERE ip_address = $1 AND success = false AND created_at > $2
+            "#,
+            ip_address,
+            since
+        )
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(count.unwrap_or(0))
+    }
+
+    async fn get_login_attempts(&self, user_id: Uuid) -> Result<Vec<LoginAttempt>, Error> {
+        let attempts = sqlx::query_as!(
+            LoginAttempt,
+            r#"SELECT id, user_id, email, ip_address, success, created_at FROM login_attempts WHERE user_id = $1 ORDER BY created_at DESC"#,
+            user_id
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(attempts)
+    }
+}
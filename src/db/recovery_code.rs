@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use sqlx::Error;
+use uuid::Uuid;
+
+use super::DBClient;
+use crate::models::RecoveryCode;
+
+/// 恢复码数据库操作扩展特征 -- 定义了两步验证恢复码的生成、消费与统计
+#[async_trait]
+pub trait RecoveryCodeExt {
+    /// 替换用户的全部恢复码 -- 删除旧恢复码并保存新的哈希
+    ///
+    /// # 参数
+    /// - `user_id` -- 用户ID
+    /// - `code_hashes` -- 恢复码的 argon2 哈希
+    async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code_hashes: &[String],
+    ) -> Result<(), Error>;
+
+    /// 获取用户尚未使用的恢复码
+    async fn get_unused_recovery_codes(&self, user_id: Uuid) -> Result<Vec<RecoveryCode>, Error>;
+
+    /// 消费恢复码 -- 只有尚未使用的恢复码才会被标记
+    ///
+    /// # 返回
+    /// - `Ok(true)` -- 消费成功
+    /// - `Ok(false)` -- 恢复码已被使用
+    async fn use_recovery_code(&self, code_id: Uuid) -> Result<bool, Error>;
+
+    /// 统计用户剩余可用的恢复码数量
+    async fn count_unused_recovery_codes(&self, user_id: Uuid) -> Result<i64, Error>;
+
+    /// 删除用户的全部恢复码 -- 关闭两步验证时调用
+    async fn delete_recovery_codes(&self, user_id: Uuid) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl RecoveryCodeExt for DBClient {
+    async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code_hashes: &[String],
+    ) -> Result<(), Error> {
+        let mut tx = self.pool().begin().await?;
+
+        let _ = sqlx::query!(
+            r#"DELETE FROM mfa_recovery_codes WHERE user_id = $1"#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO mfa_recovery_codes (user_id, code_hash)
+            SELECT $1, UNNEST($2::VARCHAR[])
+            "#,
+            user_id,
+            code_hashes
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_unused_recovery_codes(&self, user_id: Uuid) -> Result<Vec<RecoveryCode>, Error> {
+        let codes = sqlx::query_as!(
+            RecoveryCode,
+            r#"SELECT id, user_id, code_hash, used_at, created_at FROM mfa_recovery_codes WHERE user_id = $1 AND used_at IS NULL"#,
+            user_id
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(codes)
+    }
+
+    async fn use_recovery_code(&self, code_id: Uuid) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE mfa_recovery_codes
+            SET used_at = Now()
+            WHERE id = $1 AND used_at IS NULL
+            "#,
+            code_id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_unused_recovery_codes(&self, user_id: Uuid) -> Result<i64, Error> {
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) FROM mfa_recovery_codes WHERE user_id = $1 AND used_at IS NULL"#,
+            user_id
+        )
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(count.unwrap_or(0))
+    }
+
+    async fn delete_recovery_codes(&self, user_id: Uuid) -> Result<(), Error> {
+        let _ = sqlx::query!(
+            r#"DELETE FROM mfa_recovery_codes WHERE user_id = $1"#,
+            user_id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use async_trait::async_trait;
+use sqlx::Error;
+
+use super::DBClient;
+use crate::models::{PermissionInfo, Role};
+
+/// 角色数据库操作扩展特征 -- 定义了角色及其权限的查询与维护
+#[async_trait]
+pub trait RoleExt {
+    /// 获取全部角色及其权限，按名称排序
+    async fn get_roles(&self) -> Result<Vec<Role>, Error>;
+
+    /// 按名称获取角色及其权限
+    async fn get_role(&self, name: &str) -> Result<Option<Role>, Error>;
+
+    /// 获取角色拥有的权限名称 -- 角色不存在时返回空列表
+    async fn get_role_permissions(&self, name: &str) -> Result<Vec<String>, Error>;
+
+    /// 获取全部权限定义
+    async fn get_permissions(&self) -> Result<Vec<PermissionInfo>, Error>;
+
+    /// 创建角色并授予权限
+    ///
+    /// 名称重复时违反唯一约束，权限不存在时违反外键约束，均返回数据库错误
+    async fn create_role(
+        &self,
+        name: &str,
+        description: &str,
+        permissions: &[String],
+    ) -> Result<Role, Error>;
+
+    /// 修改角色 -- 只更新传入的字段，传入权限时替换角色的全部权限
+    ///
+    /// # 返回
+    /// - `Ok(Some(role))` -- 修改后的角色
+    /// - `Ok(None)` -- 角色不存在
+    async fn update_role(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        permissions: Option<&[String]>,
+    ) -> Result<Option<Role>, Error>;
+
+    /// 删除角色 -- 仍有用户使用该角色时违反外键约束，返回数据库错误
+    ///
+    /// # 返回
+    /// - `Ok(true)` -- 删除成功
+    /// - `Ok(false)` -- 角色不存在
+    async fn delete_role(&self, name: &str) -> Result<bool, Error>;
+}
+
+#[async_trait]
+impl RoleExt for DBClient {
+    async fn get_roles(&self) -> Result<Vec<Role>, Error> {
+        let roles = sqlx::query_as!(
+            Role,
+            r#"
+            SELECT r.name, r.description, r.is_system, r.created_at, r.updated_at,
+                   COALESCE(ARRAY_AGG(rp.permission ORDER BY rp.permission) FILTER (WHERE rp.permission IS NOT NULL), '{}') as "permissions!"
+            FROM roles r
+            LEFT JOIN role_permissions rp ON rp.role_name = r.name
+            GROUP BY r.name
+            ORDER BY r.name
+            "#
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(roles)
+    }
+
+    async fn get_role(&self, name: &str) -> Result<Option<Role>, Error> {
+        let role = sqlx::query_as!(
+            Role,
+            r#"
+            SELECT r.name, r.description, r.is_system, r.created_at, r.updated_at,
+                   COALESCE(ARRAY_AGG(rp.permission ORDER BY rp.permission) FILTER (WHERE rp.permission IS NOT NULL), '{}') as "permissions!"
+            FROM roles r
+            LEFT JOIN role_permissions rp ON rp.role_name = r.name
+            WHERE r.name = $1
+            GROUP BY r.name
+            "#,
+            name
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(role)
+    }
+
+    async fn get_role_permissions(&self, name: &str) -> Result<Vec<String>, Error> {
+        let permissions = sqlx::query_scalar!(
+            r#"SELECT permission FROM role_permissions WHERE role_name = $1"#,
+            name
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(permissions)
+    }
+
+    async fn get_permissions(&self) -> Result<Vec<PermissionInfo>, Error> {
+        let permissions = sqlx::query_as!(
+            PermissionInfo,
+            r#"SELECT name, description FROM permissions ORDER BY name"#
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(permissions)
+    }
+
+    async fn create_role(
+        &self,
+        name: &str,
+        description: &str,
+        permissions: &[String],
+    ) -> Result<Role, Error> {
+        let mut tx = self.pool().begin().await?;
+
+        let _ = sqlx::query!(
+            r#"INSERT INTO roles (name, description) VALUES ($1, $2)"#,
+            name,
+            description
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO role_permissions (role_name, permission)
+            SELECT $1, UNNEST($2::VARCHAR[])
+            ON CONFLICT DO NOTHING
+            "#,
+            name,
+            permissions
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.get_role(name).await?.ok_or(Error::RowNotFound)
+    }
+
+    async fn update_role(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        permissions: Option<&[String]>,
+    ) -> Result<Option<Role>, Error> {
+        let mut tx = self.pool().begin().await?;
+
+        let updated = sqlx::query!(
+            r#"
+            UPDATE roles
+            SET description = COALESCE($2, description), updated_at = Now()
+            WHERE name = $1
+            "#,
+            name,
+            description
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        if let Some(permissions) = permissions {
+            let _ = sqlx::query!(r#"DELETE FROM role_permissions WHERE role_name = $1"#, name)
+                .execute(&mut *tx)
+                .await?;
+
+            let _ = sqlx::query!(
+                r#"
+                INSERT INTO role_permissions (role_name, permission)
+                SELECT $1, UNNEST($2::VARCHAR[])
+                ON CONFLICT DO NOTHING
+                "#,
+                name,
+                permissions
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        self.get_role(name).await
+    }
+
+    async fn delete_role(&self, name: &str) -> Result<bool, Error> {
+        let result = sqlx::query!(r#"DELETE FROM roles WHERE name = $1"#, name)
+            .execute(self.pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
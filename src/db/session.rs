@@ -0,0 +1,242 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Error;
+use uuid::Uuid;
+
+use super::DBClient;
+use crate::models::Session;
+
+/// 会话数据库操作扩展特征 -- 定义了刷新令牌会话的存储、轮换与吊销
+#[async_trait]
+pub trait SessionExt {
+    /// 创建会话 -- 保存刷新令牌的哈希值
+    ///
+    /// # 参数
+    /// - `user_id` -- 用户ID
+    /// - `family_id` -- 会话族ID，同一次登录轮换出的所有令牌共享该值
+    /// - `refresh_token_hash` -- 刷新令牌的 SHA-256 哈希
+    /// - `expires_at` -- 刷新令牌过期时间
+    async fn save_session(
+        &self,
+        user_id: Uuid,
+        family_id: Uuid,
+        refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Session, Error>;
+
+    /// 根据刷新令牌哈希查找会话
+    ///
+    /// # 返回
+    /// - `Ok(Some(Session))` -- 查找到会话（可能已轮换或已吊销）
+    /// - `Ok(None)` -- 未找到会话
+    async fn get_session_by_token_hash(
+        &self,
+        refresh_token_hash: &str,
+    ) -> Result<Option<Session>, Error>;
+
+    /// 轮换会话 -- 将旧令牌标记为已轮换，并在同一会话族下保存新令牌
+    ///
+    /// # 返回
+    /// - `Ok(Some(Session))` -- 轮换成功，返回新会话
+    /// - `Ok(None)` -- 旧令牌已被轮换或吊销（并发重放）
+    async fn rotate_session(
+        &self,
+        session_id: Uuid,
+        refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<Session>, Error>;
+
+    /// 吊销整个会话族 -- 检测到刷新令牌重放或退出登录时调用
+    async fn revoke_session_family(&self, family_id: Uuid) -> Result<(), Error>;
+
+    /// 吊销用户的所有会话 -- 退出所有设备、修改或重置密码时调用
+    async fn revoke_user_sessions(&self, user_id: Uuid) -> Result<(), Error>;
+
+    /// 获取用户的全部会话记录（包括已轮换和已吊销的），按创建时间倒序
+    async fn get_user_sessions(&self, user_id: Uuid) -> Result<Vec<Session>, Error>;
+
+    /// 将访问令牌加入黑名单 -- 记录保留到令牌自然过期为止
+    ///
+    /// # 参数
+    /// - `jti` -- 访问令牌的唯一标识
+    /// - `user_id` -- 令牌所属用户ID
+    /// - `expires_at` -- 令牌过期时间
+    async fn revoke_token(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), Error>;
+
+    /// 检查访问令牌是否已被吊销
+    async fn is_token_revoked(&self, jti: Uuid) -> Result<bool, Error>;
+}
+
+#[async_trait]
+impl SessionExt for DBClient {
+    async fn save_session(
+        &self,
+        user_id: Uuid,
+        family_id: Uuid,
+        refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Session, Error> {
+        let session = sqlx::query_as!(
+            Session,
+            r#"
+            INSERT INTO sessions (user_id, family_id, refresh_token_hash, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, user_id, family_id, refresh_token_hash, expires_at, rotated_at, revoked_at, created_at
+            "#,
+            user_id,
+            family_id,
+            refresh_token_hash,
+            expires_at
+        )
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(session)
+    }
+
+    async fn get_session_by_token_hash(
+        &self,
+        refresh_token_hash: &str,
+    ) -> Result<Option<Session>, Error> {
+        let session = sqlx::query_as!(
+            Session,
+            r#"SELECT id, user_id, family_id, refresh_token_hash, expires_at, rotated_at, revoked_at, created_at FROM sessions WHERE refresh_token_hash = $1"#,
+            refresh_token_hash
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(session)
+    }
+
+    async fn rotate_session(
+        &self,
+        session_id: Uuid,
+        refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<Session>, Error> {
+        let mut tx = self.pool().begin().await?;
+
+        // -- 只有尚未轮换且未吊销的令牌才能被轮换，防止并发请求重复使用同一令牌
+        let rotated = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET rotated_at = Now()
+            WHERE id = $1 AND rotated_at IS NULL AND revoked_at IS NULL
+            RETURNING user_id, family_id
+            "#,
+            session_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(rotated) = rotated else {
+            tx.rollback().await?;
+            return Ok(None);
+        };
+
+        let session = sqlx::query_as!(
+            Session,
+            r#"
+            INSERT INTO sessions (user_id, family_id, refresh_token_hash, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, user_id, family_id, refresh_token_hash, expires_at, rotated_at, revoked_at, created_at
+            "#,
+            rotated.user_id,
+            rotated.family_id,
+            refresh_token_hash,
+            expires_at
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(session))
+    }
+
+    async fn revoke_session_family(&self, family_id: Uuid) -> Result<(), Error> {
+        let _ = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET revoked_at = Now()
+            WHERE family_id = $1 AND revoked_at IS NULL
+            "#,
+            family_id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_user_sessions(&self, user_id: Uuid) -> Result<(), Error> {
+        let _ = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET revoked_at = Now()
+            WHERE user_id = $1 AND revoked_at IS NULL
+            "#,
+            user_id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_user_sessions(&self, user_id: Uuid) -> Result<Vec<Session>, Error> {
+        let sessions = sqlx::query_as!(
+            Session,
+            r#"SELECT id, user_id, family_id, refresh_token_hash, expires_at, rotated_at, revoked_at, created_at FROM sessions WHERE user_id = $1 ORDER BY created_at DESC"#,
+            user_id
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn revoke_token(
+        &self,
+        jti: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        // -- 顺带清理已自然过期的黑名单记录，避免表无限增长
+        let _ = sqlx::query!(r#"DELETE FROM revoked_tokens WHERE expires_at < Now()"#)
+            .execute(self.pool())
+            .await?;
+
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO revoked_tokens (jti, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+            jti,
+            user_id,
+            expires_at
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn is_token_revoked(&self, jti: Uuid) -> Result<bool, Error> {
+        let revoked = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM revoked_tokens WHERE jti = $1)"#,
+            jti
+        )
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(revoked.unwrap_or(false))
+    }
+}
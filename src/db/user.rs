@@ -3,8 +3,8 @@ use chrono::{DateTime, FixedOffset, TimeZone, Utc};
 use sqlx::Error;
 use uuid::Uuid;
 
-use super::DBClient;
-use crate::models::{User, UserRole};
+use super::{DBClient, DbError};
+use crate::models::{User, UserStatus, UserTokenPurpose, ADMIN_ROLE};
 
 /// 时区转换辅助函数 -- 将本地时间转换为 UTC 时间
 fn to_utc(local_time: DateTime<FixedOffset>) -> DateTime<Utc> {
@@ -17,6 +17,15 @@ fn to_local(utc_time: DateTime<Utc>) -> DateTime<FixedOffset> {
     utc_time.with_timezone(&china_timezone)
 }
 
+/// 管理员修改用户资料时的字段 -- `None` 表示保持不变
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UserUpdate<'a> {
+    pub name: Option<&'a str>,
+    pub email: Option<&'a str>,
+    pub role: Option<&'a str>,
+    pub verified: Option<bool>,
+}
+
 /// 用户数据库操作扩展特征 -- 定义了所有与用户相关的数据库操作
 #[async_trait]
 pub trait UserExt {
@@ -26,7 +35,6 @@ pub trait UserExt {
     /// - `user_id` -- 用户ID
     /// - `name` -- 用户名
     /// - `email` -- 用户邮箱
-    /// - `token` -- 验证令牌
     ///
     /// # 返回
     /// - `Ok(Some(User))` -- 查找到用户
@@ -37,7 +45,6 @@ pub trait UserExt {
         user_id: Option<Uuid>,
         name: Option<&str>,
         email: Option<&str>,
-        token: Option<&str>,
     ) -> Result<Option<User>, Error>;
 
     /// 分页获取用户列表 -- 按创建时间倒序排列
@@ -47,20 +54,20 @@ pub trait UserExt {
     /// - `limit` -- 每页数量
     async fn get_users(&self, page: u32, limit: usize) -> Result<Vec<User>, Error>;
 
-    /// 保存新用户 -- 创建新的用户记录
+    /// 保存新用户 -- 创建新的用户记录，同时保存邮箱验证令牌
     ///
     /// # 参数
     /// - `name` -- 用户名
     /// - `email` -- 邮箱
     /// - `password` -- 密码（已哈希）
-    /// - `verification_token` -- 验证令牌
+    /// - `verification_token_hash` -- 邮箱验证令牌的哈希
     /// - `token_expires_at` -- 令牌过期时间
     async fn save_user<T: Into<String> + Send>(
         &self,
         name: T,
         email: T,
         password: T,
-        verification_token: T,
+        verification_token_hash: T,
         token_expires_at: DateTime<Utc>,
     ) -> Result<User, Error>;
 
@@ -74,22 +81,172 @@ pub trait UserExt {
         name: T,
     ) -> Result<User, Error>;
 
-    /// 更新用户角色 -- 修改用户的权限级别
-    async fn update_user_role(&self, user_id: Uuid, role: UserRole) -> Result<User, Error>;
+    /// 管理员修改用户资料 -- 只更新传入的字段，角色发生变化时递增令牌版本
+    ///
+    /// 邮箱发生变化时将邮箱标记为未验证、递增令牌版本并吊销全部会话，新邮箱需要重新验证
+    ///
+    /// # 错误类型
+    /// - `UserNotFound` -- 用户不存在
+    /// - `EmailExists` -- 邮箱已被其他用户使用
+    /// - `RoleNotFound` -- 角色不存在
+    /// - `LastAdmin` -- 将最后一个正常状态的管理员改为其他角色
+    async fn admin_update_user(
+        &self,
+        user_id: Uuid,
+        update: UserUpdate<'_>,
+    ) -> Result<User, DbError>;
 
-    /// 更新用户密码 -- 修改用户的登录密码
-    async fn update_user_password(&self, user_id: Uuid, password: String) -> Result<User, Error>;
+    /// 修改账户状态 -- 改为非正常状态时递增令牌版本，已签发的访问令牌全部失效
+    ///
+    /// # 参数
+    /// - `status` -- 新状态
+    /// - `reason` -- 修改原因
+    /// - `suspended_until` -- 暂停截止时间，只在暂停状态下保存
+    ///
+    /// # 错误类型
+    /// - `UserNotFound` -- 用户不存在
+    /// - `LastAdmin` -- 将最后一个正常状态的管理员改为其他状态
+    async fn set_user_status(
+        &self,
+        user_id: Uuid,
+        status: UserStatus,
+        reason: Option<&str>,
+        suspended_until: Option<DateTime<Utc>>,
+    ) -> Result<User, DbError>;
 
-    /// 验证用户令牌 -- 确认邮箱验证或重置密码
-    async fn verified_token(&self, token: &str) -> Result<(), Error>;
+    /// 恢复暂停已到期的账户 -- 由后台任务定期调用，返回被恢复的用户ID
+    async fn lift_expired_suspensions(&self) -> Result<Vec<Uuid>, Error>;
 
-    /// 添加验证令牌 -- 用于邮箱验证或密码重置
-    async fn add_verified_token(
+    /// 删除用户 -- 会话、身份、密钥等关联数据通过外键级联删除
+    ///
+    /// # 错误类型
+    /// - `UserNotFound` -- 用户不存在
+    /// - `LastAdmin` -- 删除最后一个正常状态的管理员
+    async fn delete_user(&self, user_id: Uuid) -> Result<(), DbError>;
+
+    /// 用户申请删除账户 -- 标记删除时间并递增令牌版本，宽限期内登录会恢复账户
+    ///
+    /// # 错误类型
+    /// - `UserNotFound` -- 用户不存在或已申请删除
+    /// - `LastAdmin` -- 最后一个正常状态的管理员申请删除
+    async fn request_account_deletion(&self, user_id: Uuid) -> Result<User, DbError>;
+
+    /// 撤销删除申请 -- 只恢复仍在 `grace_days` 天宽限期内的账户，返回是否恢复成功
+    async fn restore_deleted_user(&self, user_id: Uuid, grace_days: i64) -> Result<bool, Error>;
+
+    /// 彻底删除申请删除时间早于 `before` 的账户 -- 由后台任务定期调用，返回被删除的用户ID
+    ///
+    /// 关联数据通过外键级联删除，不受外键约束的登录尝试记录按邮箱删除，
+    /// 保留的审计事件中移除名称和邮箱
+    async fn purge_deleted_users(&self, before: DateTime<Utc>) -> Result<Vec<Uuid>, Error>;
+
+    /// 更新用户密码 -- 修改用户的登录密码，同时递增令牌版本使已签发的访问令牌全部失效
+    ///
+    /// 旧密码写入密码历史，历史中只保留最近的 `history_size - 1` 个旧密码（加上当前密码共 `history_size` 个）
+    async fn update_user_password(
         &self,
         user_id: Uuid,
-        token: &str,
+        password: String,
+        history_size: usize,
+    ) -> Result<User, Error>;
+
+    /// 升级密码哈希 -- 只替换哈希格式，不视为修改密码（不递增令牌版本、不写入密码历史）
+    ///
+    /// 只有当前哈希仍为 `old_hash` 时才会更新，避免覆盖并发修改的新密码
+    async fn upgrade_password_hash(
+        &self,
+        user_id: Uuid,
+        old_hash: &str,
+        new_hash: &str,
+    ) -> Result<(), Error>;
+
+    /// 获取用户最近使用过的旧密码哈希（不含当前密码），按时间倒序
+    async fn get_password_history(&self, user_id: Uuid, limit: usize)
+        -> Result<Vec<String>, Error>;
+
+    /// 递增令牌版本 -- 使该用户已签发的所有访问令牌失效（退出所有设备）
+    async fn increment_token_version(&self, user_id: Uuid) -> Result<(), Error>;
+
+    /// 保存待确认的 TOTP 密钥 -- 确认前不会影响当前已启用的密钥
+    async fn set_totp_pending_secret(&self, user_id: Uuid, secret: &str) -> Result<(), Error>;
+
+    /// 启用 TOTP -- 将待确认密钥设为当前密钥并开启两步验证
+    ///
+    /// # 参数
+    /// - `user_id` -- 用户ID
+    /// - `last_used_step` -- 确认时使用的验证码时间步，防止同一验证码再次用于登录
+    async fn enable_totp(&self, user_id: Uuid, last_used_step: i64) -> Result<User, Error>;
+
+    /// 关闭 TOTP -- 清除所有密钥并关闭两步验证
+    async fn disable_totp(&self, user_id: Uuid) -> Result<User, Error>;
+
+    /// 记录已使用的 TOTP 时间步 -- 只有时间步递增时才会更新
+    ///
+    /// # 返回
+    /// - `Ok(true)` -- 更新成功
+    /// - `Ok(false)` -- 该时间步已被使用（验证码重放）
+    async fn update_totp_last_used_step(&self, user_id: Uuid, step: i64) -> Result<bool, Error>;
+
+    /// 记录一次密码错误 -- 连续失败次数达到上限时锁定账户并重新计数
+    ///
+    /// # 参数
+    /// - `user_id` -- 用户ID
+    /// - `max_attempts` -- 锁定前允许的连续失败次数
+    /// - `lock_until` -- 达到上限时的锁定截止时间
+    async fn record_failed_login(
+        &self,
+        user_id: Uuid,
+        max_attempts: i32,
+        lock_until: DateTime<Utc>,
+    ) -> Result<User, Error>;
+
+    /// 解除登录锁定 -- 清除锁定时间与连续失败次数
+    async fn unlock_user(&self, user_id: Uuid) -> Result<User, Error>;
+
+    /// 设置用户下次登录时是否必须修改密码
+    ///
+    /// 设为需要修改时同时递增令牌版本，已签发的访问令牌全部失效
+    async fn set_must_change_password(&self, user_id: Uuid, required: bool) -> Result<User, Error>;
+
+    /// 标记邮箱已验证
+    async fn verify_user_email(&self, user_id: Uuid) -> Result<User, Error>;
+
+    /// 保存邮件令牌 -- 同时作废该用户同一用途下未使用的令牌，并清理已过期的令牌
+    ///
+    /// # 参数
+    /// - `user_id` -- 用户ID
+    /// - `purpose` -- 令牌用途
+    /// - `token_hash` -- 令牌的哈希
+    /// - `expires_at` -- 令牌过期时间
+    async fn save_user_token(
+        &self,
+        user_id: Uuid,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
         expires_at: DateTime<Utc>,
     ) -> Result<(), Error>;
+
+    /// 查找有效的邮件令牌但不消费
+    ///
+    /// # 返回
+    /// - `Ok(Some(user_id))` -- 令牌有效，返回令牌所属用户ID
+    /// - `Ok(None)` -- 令牌不存在、用途不匹配、已使用或已过期
+    async fn find_user_token(
+        &self,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+    ) -> Result<Option<Uuid>, Error>;
+
+    /// 消费邮件令牌 -- 只有用途匹配、未使用且未过期的令牌才会被标记为已使用
+    ///
+    /// # 返回
+    /// - `Ok(Some(user_id))` -- 消费成功，返回令牌所属用户ID
+    /// - `Ok(None)` -- 令牌不存在、用途不匹配、已使用或已过期
+    async fn consume_user_token(
+        &self,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+    ) -> Result<Option<Uuid>, Error>;
 }
 
 #[async_trait]
@@ -99,34 +256,27 @@ impl UserExt for DBClient {
         user_id: Option<Uuid>,
         name: Option<&str>,
         email: Option<&str>,
-        token: Option<&str>,
     ) -> Result<Option<User>, Error> {
         let mut user: Option<User> = None;
 
         if let Some(user_id) = user_id {
             user = sqlx::query_as!(
                 User,
-                r#"SELECT id, name, email, password, verified, created_at, updated_at, verification_token, token_expires_at, role as "role: UserRole" FROM users WHERE id = $1"#,
+                r#"SELECT id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at FROM users WHERE id = $1"#,
                 user_id
             ).fetch_optional(self.pool()).await?;
         } else if let Some(name) = name {
             user = sqlx::query_as!(
                 User,
-                r#"SELECT id, name, email, password, verified, created_at, updated_at, verification_token, token_expires_at, role as "role: UserRole" FROM users WHERE name = $1"#,
+                r#"SELECT id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at FROM users WHERE name = $1"#,
                 name
             ).fetch_optional(self.pool()).await?;
         } else if let Some(email) = email {
             user = sqlx::query_as!(
                 User,
-                r#"SELECT id, name, email, password, verified, created_at, updated_at, verification_token, token_expires_at, role as "role: UserRole" FROM users WHERE email = $1"#,
+                r#"SELECT id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at FROM users WHERE email = $1"#,
                 email
             ).fetch_optional(self.pool()).await?;
-        } else if let Some(token) = token {
-            user = sqlx::query_as!(
-                User,
-                r#"SELECT id, name, email, password, verified, created_at, updated_at, verification_token, token_expires_at, role as "role: UserRole" FROM users WHERE verification_token = $1"#,
-                token
-            ).fetch_optional(self.pool()).await?;
         }
 
         Ok(user)
@@ -137,7 +287,7 @@ impl UserExt for DBClient {
 
         let users = sqlx::query_as!(
             User,
-            r#"SELECT id, name, email, password, verified, created_at, updated_at, verification_token, token_expires_at, role as "role: UserRole" FROM users ORDER BY created_at DESC LIMIT $1 OFFSET $2"#,
+            r#"SELECT id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at FROM users ORDER BY created_at DESC LIMIT $1 OFFSET $2"#,
             limit as i64,
             offset as i64,
         ).fetch_all(self.pool())
@@ -151,23 +301,39 @@ impl UserExt for DBClient {
         name: T,
         email: T,
         password: T,
-        verification_token: T,
+        verification_token_hash: T,
         token_expires_at: DateTime<Utc>,
     ) -> Result<User, Error> {
+        let mut tx = self.pool().begin().await?;
+
         let user = sqlx::query_as!(
             User,
             r#"
-            INSERT INTO users (name, email, password, verification_token, token_expires_at) 
-            VALUES ($1, $2, $3, $4, $5) 
-            RETURNING id, name, email, password, verified, created_at, updated_at, verification_token, token_expires_at, role as "role: UserRole"
+            INSERT INTO users (name, email, password) 
+            VALUES ($1, $2, $3) 
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
             "#,
             name.into(),
             email.into(),
-            password.into(),
-            verification_token.into(),
+            password.into()
+        ).fetch_one(&mut *tx)
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            user.id,
+            UserTokenPurpose::VerifyEmail as UserTokenPurpose,
+            verification_token_hash.into(),
             token_expires_at
-        ).fetch_one(self.pool())
+        )
+        .execute(&mut *tx)
         .await?;
+
+        tx.commit().await?;
+
         Ok(user)
     }
 
@@ -190,7 +356,7 @@ impl UserExt for DBClient {
             UPDATE users
             SET name = $1, updated_at = Now()
             WHERE id = $2
-            RETURNING id, name, email, password, verified, created_at, updated_at, verification_token, token_expires_at, role as "role: UserRole"
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
             "#,
             new_name.into(),
             user_id
@@ -200,55 +366,354 @@ impl UserExt for DBClient {
         Ok(user)
     }
 
-    async fn update_user_role(&self, user_id: Uuid, new_role: UserRole) -> Result<User, Error> {
+    async fn admin_update_user(
+        &self,
+        user_id: Uuid,
+        update: UserUpdate<'_>,
+    ) -> Result<User, DbError> {
+        let mut tx = self.pool().begin().await?;
+
+        let admins = lock_active_admins(&mut tx).await?;
+        if update.role.is_some_and(|role| role != ADMIN_ROLE) && admins == [user_id] {
+            return Err(DbError::LastAdmin);
+        }
+
+        let previous_email = sqlx::query_scalar!(
+            r#"SELECT email FROM users WHERE id = $1 FOR UPDATE"#,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(DbError::UserNotFound)?;
+
+        // -- 新邮箱未经验证，不能沿用旧邮箱的验证状态，否则可以把账户指向他人邮箱后通过忘记密码接管
+        let email_changed = update.email.is_some_and(|email| email != previous_email);
+
         let user = sqlx::query_as!(
             User,
             r#"
             UPDATE users
-            SET role = $1, updated_at = Now()
-            WHERE id = $2
-            RETURNING id, name, email, password, verified, created_at, updated_at, verification_token, token_expires_at, role as "role: UserRole"
+            SET name = COALESCE($2, name),
+                email = COALESCE($3, email),
+                role = COALESCE($4, role),
+                verified = CASE WHEN $6 THEN FALSE ELSE COALESCE($5, verified) END,
+                token_version = CASE WHEN $6 OR ($4::VARCHAR IS NOT NULL AND $4 <> role) THEN token_version + 1 ELSE token_version END,
+                updated_at = Now()
+            WHERE id = $1
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
             "#,
-            new_role as UserRole,
-            user_id
-        ).fetch_one(self.pool())
+            user_id,
+            update.name,
+            update.email,
+            update.role,
+            update.verified,
+            email_changed
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            Error::Database(db_err) if db_err.is_unique_violation() => DbError::EmailExists,
+            Error::Database(db_err) if db_err.is_foreign_key_violation() => DbError::RoleNotFound,
+            e => DbError::Sqlx(e),
+        })?
+        .ok_or(DbError::UserNotFound)?;
+
+        if email_changed {
+            let _ = sqlx::query!(
+                r#"UPDATE sessions SET revoked_at = Now() WHERE user_id = $1 AND revoked_at IS NULL"#,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    async fn set_user_status(
+        &self,
+        user_id: Uuid,
+        status: UserStatus,
+        reason: Option<&str>,
+        suspended_until: Option<DateTime<Utc>>,
+    ) -> Result<User, DbError> {
+        let mut tx = self.pool().begin().await?;
+
+        let admins = lock_active_admins(&mut tx).await?;
+        if status != UserStatus::Active && admins == [user_id] {
+            return Err(DbError::LastAdmin);
+        }
+
+        let suspended_until = suspended_until.filter(|_| status == UserStatus::Suspended);
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET status = $2,
+                status_reason = $3,
+                suspended_until = $4,
+                token_version = CASE WHEN $2 <> 'active'::user_status THEN token_version + 1 ELSE token_version END,
+                updated_at = Now()
+            WHERE id = $1
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
+            "#,
+            user_id,
+            status as UserStatus,
+            reason,
+            suspended_until
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(DbError::UserNotFound)?;
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    async fn lift_expired_suspensions(&self) -> Result<Vec<Uuid>, Error> {
+        let user_ids = sqlx::query_scalar!(
+            r#"
+            UPDATE users
+            SET status = 'active', status_reason = NULL, suspended_until = NULL, updated_at = Now()
+            WHERE status = 'suspended' AND suspended_until <= Now()
+            RETURNING id
+            "#
+        )
+        .fetch_all(self.pool())
         .await?;
 
+        Ok(user_ids)
+    }
+
+    async fn delete_user(&self, user_id: Uuid) -> Result<(), DbError> {
+        let mut tx = self.pool().begin().await?;
+
+        let admins = lock_active_admins(&mut tx).await?;
+        if admins == [user_id] {
+            return Err(DbError::LastAdmin);
+        }
+
+        let result = sqlx::query!(r#"DELETE FROM users WHERE id = $1"#, user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DbError::UserNotFound);
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn request_account_deletion(&self, user_id: Uuid) -> Result<User, DbError> {
+        let mut tx = self.pool().begin().await?;
+
+        let admins = lock_active_admins(&mut tx).await?;
+        if admins == [user_id] {
+            return Err(DbError::LastAdmin);
+        }
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET deleted_at = Now(), token_version = token_version + 1, updated_at = Now()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
+            "#,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(DbError::UserNotFound)?;
+
+        tx.commit().await?;
+
         Ok(user)
     }
 
+    async fn restore_deleted_user(&self, user_id: Uuid, grace_days: i64) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET deleted_at = NULL, updated_at = Now()
+            WHERE id = $1 AND deleted_at > Now() - make_interval(days => $2)
+            "#,
+            user_id,
+            grace_days as i32
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn purge_deleted_users(&self, before: DateTime<Utc>) -> Result<Vec<Uuid>, Error> {
+        let mut tx = self.pool().begin().await?;
+
+        let deleted = sqlx::query!(
+            r#"DELETE FROM users WHERE deleted_at <= $1 RETURNING id, email"#,
+            before
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let (user_ids, emails): (Vec<Uuid>, Vec<String>) =
+            deleted.into_iter().map(|row| (row.id, row.email)).unzip();
+
+        if !user_ids.is_empty() {
+            // -- 邮箱未注册时记录的登录尝试没有用户ID，不会被级联删除
+            sqlx::query!(
+                r#"DELETE FROM login_attempts WHERE email = ANY($1)"#,
+                &emails
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                UPDATE audit_events
+                SET details = details - 'name' - 'email'
+                WHERE target_user_id = ANY($1)
+                "#,
+                &user_ids
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(user_ids)
+    }
+
     async fn update_user_password(
         &self,
         user_id: Uuid,
         new_password: String,
+        history_size: usize,
     ) -> Result<User, Error> {
+        let mut tx = self.pool().begin().await?;
+
+        // -- 旧密码写入密码历史
+        sqlx::query!(
+            r#"
+            INSERT INTO password_history (user_id, password_hash)
+            SELECT id, password FROM users WHERE id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
         let user = sqlx::query_as!(
             User,
             r#"
             UPDATE users
-            SET password = $1, updated_at = Now()
+            SET password = $1, token_version = token_version + 1, password_changed_at = Now(),
+                must_change_password = false, updated_at = Now()
             WHERE id = $2
-            RETURNING id, name, email, password, verified, created_at, updated_at, verification_token, token_expires_at, role as "role: UserRole"
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
             "#,
             new_password,
             user_id
-        ).fetch_one(self.pool())
+        ).fetch_one(&mut *tx)
+        .await?;
+
+        // -- 当前密码也计入最近使用的密码，历史中只保留之前的 history_size - 1 个
+        sqlx::query!(
+            r#"
+            DELETE FROM password_history
+            WHERE user_id = $1 AND id NOT IN (
+                SELECT id FROM password_history
+                WHERE user_id = $1
+                ORDER BY created_at DESC
+                LIMIT $2
+            )
+            "#,
+            user_id,
+            history_size.saturating_sub(1) as i64
+        )
+        .execute(&mut *tx)
         .await?;
 
+        tx.commit().await?;
+
         Ok(user)
     }
 
-    async fn verified_token(&self, token: &str) -> Result<(), Error> {
+    async fn upgrade_password_hash(
+        &self,
+        user_id: Uuid,
+        old_hash: &str,
+        new_hash: &str,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET password = $1
+            WHERE id = $2 AND password = $3
+            "#,
+            new_hash,
+            user_id,
+            old_hash
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_password_history(
+        &self,
+        user_id: Uuid,
+        limit: usize,
+    ) -> Result<Vec<String>, Error> {
+        let password_hashes = sqlx::query_scalar!(
+            r#"
+            SELECT password_hash FROM password_history
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+            user_id,
+            limit as i64
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(password_hashes)
+    }
+
+    async fn increment_token_version(&self, user_id: Uuid) -> Result<(), Error> {
+        let _ = sqlx::query!(
+            r#"
+            UPDATE users
+            SET token_version = token_version + 1, updated_at = Now()
+            WHERE id = $1
+            "#,
+            user_id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_totp_pending_secret(&self, user_id: Uuid, secret: &str) -> Result<(), Error> {
         let _ = sqlx::query!(
             r#"
             UPDATE users
-            SET verified = true, 
-                updated_at = Now(),
-                verification_token = NULL,
-                token_expires_at = NULL
-            WHERE verification_token = $1
+            SET totp_pending_secret = $1, updated_at = Now()
+            WHERE id = $2
             "#,
-            token
+            secret,
+            user_id
         )
         .execute(self.pool())
         .await?;
@@ -256,25 +721,313 @@ impl UserExt for DBClient {
         Ok(())
     }
 
-    async fn add_verified_token(
+    async fn enable_totp(&self, user_id: Uuid, last_used_step: i64) -> Result<User, Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET totp_secret = totp_pending_secret,
+                totp_pending_secret = NULL,
+                totp_last_used_step = $1,
+                mfa_enabled = true,
+                updated_at = Now()
+            WHERE id = $2
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
+            "#,
+            last_used_step,
+            user_id
+        ).fetch_one(self.pool())
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn disable_totp(&self, user_id: Uuid) -> Result<User, Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET totp_secret = NULL,
+                totp_pending_secret = NULL,
+                totp_last_used_step = NULL,
+                mfa_enabled = false,
+                updated_at = Now()
+            WHERE id = $1
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
+            "#,
+            user_id
+        ).fetch_one(self.pool())
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn update_totp_last_used_step(&self, user_id: Uuid, step: i64) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_last_used_step = $1
+            WHERE id = $2 AND (totp_last_used_step IS NULL OR totp_last_used_step < $1)
+            "#,
+            step,
+            user_id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn record_failed_login(
         &self,
         user_id: Uuid,
-        token: &str,
-        token_expires_at: DateTime<Utc>,
+        max_attempts: i32,
+        lock_until: DateTime<Utc>,
+    ) -> Result<User, Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET failed_login_attempts = CASE
+                    WHEN failed_login_attempts + 1 >= $2 THEN 0
+                    ELSE failed_login_attempts + 1
+                END,
+                locked_until = CASE
+                    WHEN failed_login_attempts + 1 >= $2 THEN $3
+                    ELSE locked_until
+                END
+            WHERE id = $1
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
+            "#,
+            user_id,
+            max_attempts,
+            lock_until
+        ).fetch_one(self.pool())
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn unlock_user(&self, user_id: Uuid) -> Result<User, Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET failed_login_attempts = 0, locked_until = NULL
+            WHERE id = $1
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
+            "#,
+            user_id
+        ).fetch_one(self.pool())
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn set_must_change_password(&self, user_id: Uuid, required: bool) -> Result<User, Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET must_change_password = $1,
+                token_version = CASE WHEN $1 THEN token_version + 1 ELSE token_version END,
+                updated_at = Now()
+            WHERE id = $2
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
+            "#,
+            required,
+            user_id
+        ).fetch_one(self.pool())
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn verify_user_email(&self, user_id: Uuid) -> Result<User, Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET verified = true, updated_at = Now()
+            WHERE id = $1
+            RETURNING id, name, email, password, verified, created_at, updated_at, role, token_version, mfa_enabled, totp_secret, totp_pending_secret, totp_last_used_step, failed_login_attempts, locked_until, password_changed_at, must_change_password, status as "status: UserStatus", status_reason, suspended_until, deleted_at
+            "#,
+            user_id
+        ).fetch_one(self.pool())
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn save_user_token(
+        &self,
+        user_id: Uuid,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
     ) -> Result<(), Error> {
+        let mut tx = self.pool().begin().await?;
+
         let _ = sqlx::query!(
             r#"
-            UPDATE users
-            SET verification_token = $1, token_expires_at = $2, updated_at = Now()
-            WHERE id = $3
+            DELETE FROM user_tokens
+            WHERE expires_at < Now() OR (user_id = $1 AND purpose = $2 AND used_at IS NULL)
             "#,
-            token,
-            token_expires_at,
             user_id,
+            purpose as UserTokenPurpose
         )
-        .execute(self.pool())
+        .execute(&mut *tx)
         .await?;
 
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            user_id,
+            purpose as UserTokenPurpose,
+            token_hash,
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
         Ok(())
     }
+
+    async fn find_user_token(
+        &self,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+    ) -> Result<Option<Uuid>, Error> {
+        let user_id = sqlx::query_scalar!(
+            r#"
+            SELECT user_id FROM user_tokens
+            WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > Now()
+            "#,
+            token_hash,
+            purpose as UserTokenPurpose
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(user_id)
+    }
+
+    async fn consume_user_token(
+        &self,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+    ) -> Result<Option<Uuid>, Error> {
+        let user_id = sqlx::query_scalar!(
+            r#"
+            UPDATE user_tokens
+            SET used_at = Now()
+            WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > Now()
+            RETURNING user_id
+            "#,
+            token_hash,
+            purpose as UserTokenPurpose
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(user_id)
+    }
+}
+
+/// 锁定所有正常状态的管理员并返回其ID -- 修改管理员的事务串行执行，避免并发降级后没有管理员
+///
+/// 按ID顺序加锁，并发修改不同管理员时不会死锁
+async fn lock_active_admins(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<Vec<Uuid>, Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT id FROM users
+        WHERE role = $1 AND status = 'active' AND deleted_at IS NULL
+        ORDER BY id FOR UPDATE
+        "#,
+        ADMIN_ROLE
+    )
+    .fetch_all(&mut **tx)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::db::SessionExt;
+
+    /// 创建一个已验证邮箱、带有一个登录会话的用户
+    async fn user_with_session(db: &DBClient) -> User {
+        let user = db
+            .save_user(
+                "Test User",
+                "old@example.com",
+                "password-hash",
+                "verification-token-hash",
+                Utc::now() + Duration::hours(1),
+            )
+            .await
+            .unwrap();
+        let user = db.verify_user_email(user.id).await.unwrap();
+
+        db.save_session(
+            user.id,
+            Uuid::new_v4(),
+            "refresh-token-hash",
+            Utc::now() + Duration::days(1),
+        )
+        .await
+        .unwrap();
+
+        user
+    }
+
+    #[sqlx::test]
+    async fn email_change_requires_reverification(pool: PgPool) {
+        let db = DBClient::new(pool);
+        let user = user_with_session(&db).await;
+
+        // -- 即使同时传入 `verified: true`，新邮箱也必须重新验证
+        let update = UserUpdate {
+            email: Some("new@example.com"),
+            verified: Some(true),
+            ..Default::default()
+        };
+        let updated = db.admin_update_user(user.id, update).await.unwrap();
+
+        assert_eq!(updated.email, "new@example.com");
+        assert!(!updated.verified);
+        assert_eq!(updated.token_version, user.token_version + 1);
+
+        let sessions = db.get_user_sessions(user.id).await.unwrap();
+        assert!(sessions.iter().all(|session| session.revoked_at.is_some()));
+    }
+
+    #[sqlx::test]
+    async fn unchanged_email_keeps_verification_and_sessions(pool: PgPool) {
+        let db = DBClient::new(pool);
+        let user = user_with_session(&db).await;
+
+        let update = UserUpdate {
+            name: Some("Renamed"),
+            email: Some("old@example.com"),
+            ..Default::default()
+        };
+        let updated = db.admin_update_user(user.id, update).await.unwrap();
+
+        assert_eq!(updated.name, "Renamed");
+        assert!(updated.verified);
+        assert_eq!(updated.token_version, user.token_version);
+
+        let sessions = db.get_user_sessions(user.id).await.unwrap();
+        assert!(sessions.iter().all(|session| session.revoked_at.is_none()));
+    }
 }
@@ -0,0 +1,208 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Error;
+use uuid::Uuid;
+
+use super::DBClient;
+use crate::models::{WebauthnCeremony, WebauthnChallenge, WebauthnCredential};
+
+/// WebAuthn 数据库操作扩展特征 -- 定义了通行密钥凭证与仪式挑战的存储
+#[async_trait]
+pub trait WebauthnExt {
+    /// 保存仪式挑战 -- 同时清理已过期的挑战
+    ///
+    /// # 参数
+    /// - `user_id` -- 发起仪式的用户ID，可发现凭证登录时为空
+    /// - `ceremony` -- 仪式类型
+    /// - `state` -- webauthn-rs 序列化后的服务端状态
+    /// - `expires_at` -- 挑战过期时间
+    async fn save_webauthn_challenge(
+        &self,
+        user_id: Option<Uuid>,
+        ceremony: WebauthnCeremony,
+        state: serde_json::Value,
+        expires_at: DateTime<Utc>,
+    ) -> Result<WebauthnChallenge, Error>;
+
+    /// 取出仪式挑战 -- 取出即删除，保证每个挑战只能使用一次
+    async fn take_webauthn_challenge(
+        &self,
+        challenge_id: Uuid,
+        ceremony: WebauthnCeremony,
+    ) -> Result<Option<WebauthnChallenge>, Error>;
+
+    /// 保存通行密钥凭证
+    async fn save_webauthn_credential(
+        &self,
+        user_id: Uuid,
+        credential_id: &[u8],
+        name: &str,
+        passkey: serde_json::Value,
+    ) -> Result<WebauthnCredential, Error>;
+
+    /// 获取用户的全部通行密钥凭证
+    async fn get_webauthn_credentials(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<WebauthnCredential>, Error>;
+
+    /// 根据凭证ID查找通行密钥凭证
+    async fn get_webauthn_credential_by_credential_id(
+        &self,
+        credential_id: &[u8],
+    ) -> Result<Option<WebauthnCredential>, Error>;
+
+    /// 更新通行密钥凭证 -- 认证成功后保存新的计数器并记录使用时间
+    async fn update_webauthn_credential(
+        &self,
+        id: Uuid,
+        passkey: serde_json::Value,
+    ) -> Result<(), Error>;
+
+    /// 删除用户的通行密钥凭证
+    ///
+    /// # 返回
+    /// - `Ok(true)` -- 删除成功
+    /// - `Ok(false)` -- 凭证不存在或不属于该用户
+    async fn delete_webauthn_credential(&self, user_id: Uuid, id: Uuid) -> Result<bool, Error>;
+}
+
+#[async_trait]
+impl WebauthnExt for DBClient {
+    async fn save_webauthn_challenge(
+        &self,
+        user_id: Option<Uuid>,
+        ceremony: WebauthnCeremony,
+        state: serde_json::Value,
+        expires_at: DateTime<Utc>,
+    ) -> Result<WebauthnChallenge, Error> {
+        let _ = sqlx::query!(r#"DELETE FROM webauthn_challenges WHERE expires_at < Now()"#)
+            .execute(self.pool())
+            .await?;
+
+        let challenge = sqlx::query_as!(
+            WebauthnChallenge,
+            r#"
+            INSERT INTO webauthn_challenges (user_id, ceremony, state, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, user_id, ceremony as "ceremony: WebauthnCeremony", state, expires_at, created_at
+            "#,
+            user_id,
+            ceremony as WebauthnCeremony,
+            state,
+            expires_at
+        )
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(challenge)
+    }
+
+    async fn take_webauthn_challenge(
+        &self,
+        challenge_id: Uuid,
+        ceremony: WebauthnCeremony,
+    ) -> Result<Option<WebauthnChallenge>, Error> {
+        let challenge = sqlx::query_as!(
+            WebauthnChallenge,
+            r#"
+            DELETE FROM webauthn_challenges
+            WHERE id = $1 AND ceremony = $2
+            RETURNING id, user_id, ceremony as "ceremony: WebauthnCeremony", state, expires_at, created_at
+            "#,
+            challenge_id,
+            ceremony as WebauthnCeremony
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(challenge)
+    }
+
+    async fn save_webauthn_credential(
+        &self,
+        user_id: Uuid,
+        credential_id: &[u8],
+        name: &str,
+        passkey: serde_json::Value,
+    ) -> Result<WebauthnCredential, Error> {
+        let credential = sqlx::query_as!(
+            WebauthnCredential,
+            r#"
+            INSERT INTO webauthn_credentials (user_id, credential_id, name, passkey)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, user_id, credential_id, name, passkey, last_used_at, created_at
+            "#,
+            user_id,
+            credential_id,
+            name,
+            passkey
+        )
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(credential)
+    }
+
+    async fn get_webauthn_credentials(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<WebauthnCredential>, Error> {
+        let credentials = sqlx::query_as!(
+            WebauthnCredential,
+            r#"SELECT id, user_id, credential_id, name, passkey, last_used_at, created_at FROM webauthn_credentials WHERE user_id = $1 ORDER BY created_at"#,
+            user_id
+        )
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(credentials)
+    }
+
+    async fn get_webauthn_credential_by_credential_id(
+        &self,
+        credential_id: &[u8],
+    ) -> Result<Option<WebauthnCredential>, Error> {
+        let credential = sqlx::query_as!(
+            WebauthnCredential,
+            r#"SELECT id, user_id, credential_id, name, passkey, last_used_at, created_at FROM webauthn_credentials WHERE credential_id = $1"#,
+            credential_id
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(credential)
+    }
+
+    async fn update_webauthn_credential(
+        &self,
+        id: Uuid,
+        passkey: serde_json::Value,
+    ) -> Result<(), Error> {
+        let _ = sqlx::query!(
+            r#"
+            UPDATE webauthn_credentials
+            SET passkey = $1, last_used_at = Now()
+            WHERE id = $2
+            "#,
+            passkey,
+            id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_webauthn_credential(&self, user_id: Uuid, id: Uuid) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            r#"DELETE FROM webauthn_credentials WHERE id = $1 AND user_id = $2"#,
+            id,
+            user_id
+        )
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
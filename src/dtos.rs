@@ -1,9 +1,51 @@
 use chrono::{DateTime, FixedOffset, TimeZone, Utc};
 use core::str;
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationErrors};
+use webauthn_rs::prelude::{
+    CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+    RequestChallengeResponse,
+};
 
-use crate::models::{User, UserRole};
+use crate::{
+    config::PasswordPolicyConfig,
+    models::{
+        ApiKey, ApiKeyScope, AuditEvent, LoginAttempt, PermissionInfo, Role, Session, User,
+        UserIdentity, UserStatus, WebauthnCredential,
+    },
+    utils::password_policy,
+};
+
+/// 设置新密码的请求 -- 除字段校验外，新密码还需要满足密码策略
+pub trait NewPasswordDto: Validate {
+    /// 新密码字段名，策略错误归到该字段下
+    const PASSWORD_FIELD: &'static str;
+
+    fn new_password(&self) -> &str;
+
+    /// 执行字段校验和密码策略校验，所有错误一并返回
+    ///
+    /// # 参数
+    /// - `policy` -- 密码策略
+    /// - `user_inputs` -- 用户名和邮箱，密码中不能包含这些内容
+    fn validate_with_policy(
+        &self,
+        policy: &PasswordPolicyConfig,
+        user_inputs: &[&str],
+    ) -> Result<(), ValidationErrors> {
+        let mut errors = self.validate().err().unwrap_or_default();
+
+        for error in password_policy::check(policy, self.new_password(), user_inputs) {
+            errors.add(Self::PASSWORD_FIELD, error);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
 
 #[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
 pub struct RegisterUserDto {
@@ -14,17 +56,21 @@ pub struct RegisterUserDto {
         email(message = "Email is invalid")
     )]
     pub email: String,
-    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
     pub password: String,
 
-    #[validate(
-        length(min = 6, message = "Confirm Password must be at least 6 characters"),
-        must_match(other = "password", message = "passwords do not match")
-    )]
+    #[validate(must_match(other = "password", message = "passwords do not match"))]
     #[serde(rename = "passwordConfirm")]
     pub password_confirm: String,
 }
 
+impl NewPasswordDto for RegisterUserDto {
+    const PASSWORD_FIELD: &'static str = "password";
+
+    fn new_password(&self) -> &str {
+        &self.password
+    }
+}
+
 #[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
 pub struct LoginUserDto {
     #[validate(
@@ -51,6 +97,13 @@ pub struct FilterUserDto {
     pub email: String,
     pub role: String,
     pub verified: bool,
+    pub status: UserStatus,
+    #[serde(rename = "statusReason")]
+    pub status_reason: Option<String>,
+    #[serde(rename = "suspendedUntil")]
+    pub suspended_until: Option<DateTime<FixedOffset>>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<DateTime<FixedOffset>>,
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<FixedOffset>,
     #[serde(rename = "updatedAt")]
@@ -71,7 +124,15 @@ impl FilterUserDto {
             name: user.name.to_owned(),
             email: user.email.to_owned(),
             verified: user.verified,
-            role: user.role.to_str().to_string(),
+            role: user.role.clone(),
+            status: user.status,
+            status_reason: user.status_reason.clone(),
+            suspended_until: user
+                .suspended_until
+                .map(|suspended_until| suspended_until.with_timezone(&china_timezone)),
+            deleted_at: user
+                .deleted_at
+                .map(|deleted_at| deleted_at.with_timezone(&china_timezone)),
             created_at,
             updated_at,
         }
@@ -85,6 +146,11 @@ impl FilterUserDto {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserData {
     pub user: FilterUserDto,
+    #[serde(
+        rename = "recoveryCodesRemaining",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recovery_codes_remaining: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -104,6 +170,12 @@ pub struct UserListResponseDto {
 pub struct UserLoginResponseDto {
     pub status: String,
     pub token: String,
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RefreshTokenDto {
+    pub refresh_token: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -118,37 +190,62 @@ pub struct NameUpdateDto {
     pub name: String,
 }
 
+/// 管理员修改用户资料的请求 -- 未传入的字段保持不变
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
-pub struct RoleUpdateDto {
-    #[validate(custom(function = "validate_user_role"))]
-    pub role: UserRole,
+pub struct AdminUpdateUserDto {
+    #[validate(length(min = 1, message = "Name is required"))]
+    pub name: Option<String>,
+
+    #[validate(email(message = "Email is invalid"))]
+    pub email: Option<String>,
+
+    #[validate(custom(function = "validate_role_name"))]
+    pub role: Option<String>,
+
+    pub verified: Option<bool>,
 }
 
-fn validate_user_role(role: &UserRole) -> Result<(), validator::ValidationError> {
-    match role {
-        UserRole::Admin | UserRole::User => Ok(()),
-        _ => Err(validator::ValidationError::new("invalid_role")),
+/// 角色名只能包含小写字母、数字、`_` 和 `-`，长度为 1-50
+fn validate_role_name(name: &str) -> Result<(), validator::ValidationError> {
+    let valid = (1..=50).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new("invalid_role_name")
+            .with_message("Role name must be 1-50 lowercase letters, digits, '_' or '-'".into()))
     }
 }
 
 #[derive(Debug, Validate, Default, Clone, Serialize, Deserialize)]
 pub struct UserPasswordUpdateDto {
-    #[validate(length(min = 6, message = "new password must be at least 6 characters"))]
     pub new_password: String,
 
-    #[validate(
-        length(
-            min = 6,
-            message = "new password confirm must be at least 6 characters"
-        ),
-        must_match(other = "new_password", message = "new passwords do not match")
-    )]
+    #[validate(must_match(other = "new_password", message = "new passwords do not match"))]
     pub new_password_confirm: String,
 
     #[validate(length(min = 6, message = "Old password must be at least 6 characters"))]
     pub old_password: String,
 }
 
+impl NewPasswordDto for UserPasswordUpdateDto {
+    const PASSWORD_FIELD: &'static str = "new_password";
+
+    fn new_password(&self) -> &str {
+        &self.new_password
+    }
+}
+
+/// 申请删除账户 -- 需要再次输入登录密码
+#[derive(Debug, Validate, Default, Clone, Serialize, Deserialize)]
+pub struct DeleteAccountDto {
+    #[validate(length(min = 1, message = "Password is required"))]
+    pub password: String,
+}
+
 #[derive(Serialize, Deserialize, Validate)]
 pub struct VerifyEmailQueryDto {
     #[validate(length(min = 1, message = "Token is required."))]
@@ -169,21 +266,377 @@ pub struct ResetPasswordRequestDto {
     #[validate(length(min = 1, message = "Token is required."))]
     pub token: String,
 
-    #[validate(length(min = 6, message = "new password must be at least 6 characters"))]
     pub new_password: String,
 
-    #[validate(
-        length(
-            min = 6,
-            message = "new password confirm must be at least 6 characters"
-        ),
-        must_match(other = "new_password", message = "new passwords do not match")
-    )]
+    #[validate(must_match(other = "new_password", message = "new passwords do not match"))]
     pub new_password_confirm: String,
 }
 
+impl NewPasswordDto for ResetPasswordRequestDto {
+    const PASSWORD_FIELD: &'static str = "new_password";
+
+    fn new_password(&self) -> &str {
+        &self.new_password
+    }
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct ResendVerificationDto {
     #[validate(email(message = "Invalid email address"))]
     pub email: String,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaRequiredResponseDto {
+    pub status: String,
+    pub mfa_token: String,
+}
+
+/// 登录成功但必须先修改密码时的响应 -- `token` 只能用于调用修改密码接口
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordChangeRequiredResponseDto {
+    pub status: String,
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct MfaVerifyDto {
+    #[validate(length(min = 1, message = "MFA token is required"))]
+    pub mfa_token: String,
+
+    #[validate(length(equal = 6, message = "Code must be 6 digits"))]
+    pub code: Option<String>,
+
+    #[validate(length(min = 1, message = "Recovery code is required"))]
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpSetupResponseDto {
+    pub status: String,
+    pub secret: String,
+    pub otpauth_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct TotpCodeDto {
+    #[validate(length(equal = 6, message = "Code must be 6 digits"))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct TotpReauthDto {
+    #[validate(length(min = 1, message = "Password is required"))]
+    pub password: String,
+
+    #[validate(length(equal = 6, message = "Code must be 6 digits"))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponseDto {
+    pub status: String,
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyRegisterStartResponseDto {
+    pub status: String,
+    pub challenge_id: String,
+    pub options: CreationChallengeResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PasskeyRegisterFinishDto {
+    #[validate(length(min = 1, max = 100, message = "Name must be 1-100 characters"))]
+    pub name: String,
+    pub challenge_id: uuid::Uuid,
+    pub credential: RegisterPublicKeyCredential,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyAuthStartResponseDto {
+    pub status: String,
+    pub challenge_id: String,
+    pub options: RequestChallengeResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyLoginFinishDto {
+    pub challenge_id: uuid::Uuid,
+    pub credential: PublicKeyCredential,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PasskeyMfaStartDto {
+    #[validate(length(min = 1, message = "MFA token is required"))]
+    pub mfa_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PasskeyMfaFinishDto {
+    #[validate(length(min = 1, message = "MFA token is required"))]
+    pub mfa_token: String,
+    pub challenge_id: uuid::Uuid,
+    pub credential: PublicKeyCredential,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FilterPasskeyDto {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl FilterPasskeyDto {
+    pub fn filter_passkeys(credentials: &[WebauthnCredential]) -> Vec<FilterPasskeyDto> {
+        credentials
+            .iter()
+            .map(|credential| FilterPasskeyDto {
+                id: credential.id.to_string(),
+                name: credential.name.to_owned(),
+                last_used_at: credential.last_used_at,
+                created_at: credential.created_at,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyListResponseDto {
+    pub status: String,
+    pub passkeys: Vec<FilterPasskeyDto>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthProvidersResponseDto {
+    pub status: String,
+    pub providers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OAuthCallbackQueryDto {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MagicLinkRequestDto {
+    #[validate(
+        length(min = 1, message = "Email is required"),
+        email(message = "Email is invalid")
+    )]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MagicLinkQueryDto {
+    #[validate(length(min = 1, message = "Token is required."))]
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UnlockAccountQueryDto {
+    #[validate(length(min = 1, message = "Token is required."))]
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateApiKeyDto {
+    #[validate(length(min = 1, max = 100, message = "Name must be 1-100 characters"))]
+    pub name: String,
+
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    pub scopes: Vec<ApiKeyScope>,
+
+    #[validate(range(min = 1, max = 365, message = "Expiry must be between 1 and 365 days"))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FilterApiKeyDto {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(rename = "revokedAt")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl FilterApiKeyDto {
+    pub fn filter_api_key(api_key: &ApiKey) -> Self {
+        FilterApiKeyDto {
+            id: api_key.id.to_string(),
+            name: api_key.name.to_owned(),
+            prefix: api_key.prefix.to_owned(),
+            scopes: api_key.scopes.clone(),
+            expires_at: api_key.expires_at,
+            last_used_at: api_key.last_used_at,
+            revoked_at: api_key.revoked_at,
+            created_at: api_key.created_at,
+        }
+    }
+
+    pub fn filter_api_keys(api_keys: &[ApiKey]) -> Vec<FilterApiKeyDto> {
+        api_keys
+            .iter()
+            .map(FilterApiKeyDto::filter_api_key)
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyCreatedResponseDto {
+    pub status: String,
+    /// 完整密钥只在创建时返回这一次
+    pub api_key: String,
+    pub key: FilterApiKeyDto,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyListResponseDto {
+    pub status: String,
+    pub api_keys: Vec<FilterApiKeyDto>,
+}
+
+/// 会话记录 -- 不包含刷新令牌哈希
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FilterSessionDto {
+    pub id: String,
+    #[serde(rename = "familyId")]
+    pub family_id: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(rename = "rotatedAt")]
+    pub rotated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "revokedAt")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl FilterSessionDto {
+    pub fn filter_sessions(sessions: &[Session]) -> Vec<FilterSessionDto> {
+        sessions
+            .iter()
+            .map(|session| FilterSessionDto {
+                id: session.id.to_string(),
+                family_id: session.family_id.to_string(),
+                expires_at: session.expires_at,
+                rotated_at: session.rotated_at,
+                revoked_at: session.revoked_at,
+                created_at: session.created_at,
+            })
+            .collect()
+    }
+}
+
+/// 个人数据导出文件 -- 包含服务保存的该用户的全部数据，密码、密钥等凭证只导出元数据
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersonalDataExportDto {
+    #[serde(rename = "exportedAt")]
+    pub exported_at: DateTime<Utc>,
+    pub profile: FilterUserDto,
+    #[serde(rename = "mfaEnabled")]
+    pub mfa_enabled: bool,
+    #[serde(rename = "passwordChangedAt")]
+    pub password_changed_at: DateTime<Utc>,
+    pub sessions: Vec<FilterSessionDto>,
+    #[serde(rename = "loginHistory")]
+    pub login_history: Vec<LoginAttempt>,
+    #[serde(rename = "auditEvents")]
+    pub audit_events: Vec<AuditEvent>,
+    pub identities: Vec<UserIdentity>,
+    pub passkeys: Vec<FilterPasskeyDto>,
+    #[serde(rename = "apiKeys")]
+    pub api_keys: Vec<FilterApiKeyDto>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DataExportQueryDto {
+    #[validate(length(min = 1, message = "Token is required."))]
+    pub token: String,
+}
+
+/// 管理员修改账户状态的请求 -- `suspendedUntil` 只能用于暂停，为空表示无限期暂停
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_status_update"))]
+pub struct UpdateUserStatusDto {
+    pub status: UserStatus,
+
+    #[validate(length(max = 500, message = "Reason must be at most 500 characters"))]
+    pub reason: Option<String>,
+
+    #[serde(rename = "suspendedUntil")]
+    pub suspended_until: Option<DateTime<Utc>>,
+}
+
+fn validate_status_update(dto: &UpdateUserStatusDto) -> Result<(), validator::ValidationError> {
+    let Some(suspended_until) = dto.suspended_until else {
+        return Ok(());
+    };
+
+    if dto.status != UserStatus::Suspended {
+        return Err(
+            validator::ValidationError::new("suspended_until_not_allowed")
+                .with_message("suspendedUntil can only be set when suspending".into()),
+        );
+    }
+
+    if suspended_until <= Utc::now() {
+        return Err(validator::ValidationError::new("suspended_until_in_past")
+            .with_message("suspendedUntil must be in the future".into()));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateRoleDto {
+    #[validate(custom(function = "validate_role_name"))]
+    pub name: String,
+
+    #[serde(default)]
+    #[validate(length(max = 500, message = "Description must be at most 500 characters"))]
+    pub description: String,
+
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+/// 修改角色的请求 -- 未传入的字段保持不变，传入 `permissions` 时替换全部权限
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateRoleDto {
+    #[validate(length(max = 500, message = "Description must be at most 500 characters"))]
+    pub description: Option<String>,
+
+    pub permissions: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoleResponseDto {
+    pub status: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoleListResponseDto {
+    pub status: String,
+    pub roles: Vec<Role>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PermissionListResponseDto {
+    pub status: String,
+    pub permissions: Vec<PermissionInfo>,
+}
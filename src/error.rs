@@ -1,10 +1,11 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{fmt, time::Duration};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
@@ -22,6 +23,7 @@ impl fmt::Display for ErrorResponse {
 pub enum ErrorMessage {
     EmptyPassword,
     ExceededMaxPasswordLength(usize),
+    PasswordReused(usize),
     InvalidHashFormat,
     HashingError,
     InvalidToken,
@@ -32,6 +34,33 @@ pub enum ErrorMessage {
     TokenNotProvided,
     PermissionDenied,
     UserNotAuthenticated,
+    InvalidTotpSecret,
+    InvalidMfaCode,
+    MfaAlreadyEnabled,
+    MfaNotEnabled,
+    MfaSetupNotStarted,
+    InvalidRecoveryCode,
+    InvalidChallenge,
+    PasskeyVerificationFailed,
+    NoPasskeysRegistered,
+    PasskeyAlreadyRegistered,
+    InsufficientScope,
+    ApiKeyNotAllowed,
+    PasswordChangeRequired,
+    AccountLocked,
+    TooManyLoginAttempts,
+    TooManyRequests,
+    RoleNotFound,
+    RoleExists,
+    RoleInUse,
+    SystemRoleProtected,
+    UnknownPermission,
+    AccountSuspended(Option<DateTime<Utc>>),
+    AccountBanned,
+    AccountDeactivated,
+    AccountPendingDeletion,
+    LastAdmin,
+    DataExportNotFound,
 }
 
 impl fmt::Display for ErrorMessage {
@@ -55,6 +84,12 @@ impl ErrorMessage {
             ErrorMessage::ExceededMaxPasswordLength(max_length) => {
                 format!("Password must not be more than {} characters", max_length)
             }
+            ErrorMessage::PasswordReused(history_size) => {
+                format!(
+                    "New password must not match any of your last {} passwords",
+                    history_size
+                )
+            }
             ErrorMessage::InvalidToken => "Authentication token is invalid or expired".to_string(),
             ErrorMessage::TokenNotProvided => {
                 "You are not logged in, please provide a token".to_string()
@@ -65,6 +100,65 @@ impl ErrorMessage {
             ErrorMessage::UserNotAuthenticated => {
                 "Authentication required. Please log in.".to_string()
             }
+            ErrorMessage::InvalidTotpSecret => "Invalid TOTP secret".to_string(),
+            ErrorMessage::InvalidMfaCode => {
+                "Two-factor authentication code is invalid or expired".to_string()
+            }
+            ErrorMessage::MfaAlreadyEnabled => {
+                "Two-factor authentication is already enabled".to_string()
+            }
+            ErrorMessage::MfaNotEnabled => "Two-factor authentication is not enabled".to_string(),
+            ErrorMessage::MfaSetupNotStarted => {
+                "Two-factor authentication setup has not been started".to_string()
+            }
+            ErrorMessage::InvalidRecoveryCode => {
+                "Recovery code is invalid or has already been used".to_string()
+            }
+            ErrorMessage::InvalidChallenge => "Challenge is invalid or expired".to_string(),
+            ErrorMessage::PasskeyVerificationFailed => "Passkey verification failed".to_string(),
+            ErrorMessage::NoPasskeysRegistered => "No passkeys registered".to_string(),
+            ErrorMessage::PasskeyAlreadyRegistered => "Passkey already registered".to_string(),
+            ErrorMessage::InsufficientScope => {
+                "API key does not have the required scope".to_string()
+            }
+            ErrorMessage::ApiKeyNotAllowed => {
+                "This action requires a login session, API keys are not allowed".to_string()
+            }
+            ErrorMessage::PasswordChangeRequired => {
+                "You must change your password before continuing".to_string()
+            }
+            ErrorMessage::AccountLocked => {
+                "Account is temporarily locked due to too many failed login attempts".to_string()
+            }
+            ErrorMessage::TooManyLoginAttempts => {
+                "Too many failed login attempts, please try again later".to_string()
+            }
+            ErrorMessage::TooManyRequests => {
+                "Too many requests, please try again later".to_string()
+            }
+            ErrorMessage::RoleNotFound => "Role not found".to_string(),
+            ErrorMessage::RoleExists => "A role with this name already exists".to_string(),
+            ErrorMessage::RoleInUse => "Role is still assigned to users".to_string(),
+            ErrorMessage::SystemRoleProtected => {
+                "Built-in roles cannot be deleted and the admin role cannot be modified".to_string()
+            }
+            ErrorMessage::UnknownPermission => "Unknown permission".to_string(),
+            ErrorMessage::AccountSuspended(Some(suspended_until)) => format!(
+                "Your account is suspended until {}",
+                suspended_until.to_rfc3339_opts(SecondsFormat::Secs, true)
+            ),
+            ErrorMessage::AccountSuspended(None) => "Your account is suspended".to_string(),
+            ErrorMessage::AccountBanned => "Your account has been banned".to_string(),
+            ErrorMessage::AccountDeactivated => "Your account has been deactivated".to_string(),
+            ErrorMessage::AccountPendingDeletion => {
+                "Your account is scheduled for deletion, sign in again to restore it".to_string()
+            }
+            ErrorMessage::LastAdmin => {
+                "The last active admin cannot be demoted, suspended or deleted".to_string()
+            }
+            ErrorMessage::DataExportNotFound => {
+                "The download link is invalid or has expired".to_string()
+            }
         }
     }
 }
@@ -73,6 +167,8 @@ impl ErrorMessage {
 pub struct HttpError {
     pub message: String,
     pub status: StatusCode,
+    /// 限流时建议客户端等待的秒数，作为 `Retry-After` 响应头返回
+    pub retry_after: Option<u64>,
 }
 
 impl HttpError {
@@ -80,6 +176,7 @@ impl HttpError {
         HttpError {
             message: message.into(),
             status,
+            retry_after: None,
         }
     }
 
@@ -87,6 +184,7 @@ impl HttpError {
         HttpError {
             message: message.into(),
             status: StatusCode::INTERNAL_SERVER_ERROR,
+            retry_after: None,
         }
     }
 
@@ -94,6 +192,7 @@ impl HttpError {
         HttpError {
             message: message.into(),
             status: StatusCode::BAD_REQUEST,
+            retry_after: None,
         }
     }
 
@@ -101,6 +200,7 @@ impl HttpError {
         HttpError {
             message: message.into(),
             status: StatusCode::CONFLICT,
+            retry_after: None,
         }
     }
 
@@ -108,6 +208,16 @@ impl HttpError {
         HttpError {
             message: message.into(),
             status: StatusCode::UNAUTHORIZED,
+            retry_after: None,
+        }
+    }
+
+    pub fn too_many_requests(message: impl Into<String>, retry_after: Duration) -> Self {
+        HttpError {
+            message: message.into(),
+            status: StatusCode::TOO_MANY_REQUESTS,
+            // -- 向上取整，避免客户端在限制解除前重试
+            retry_after: Some(retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)),
         }
     }
 
@@ -117,7 +227,14 @@ impl HttpError {
             message: self.message.clone(),
         });
 
-        (self.status, json_response).into_response()
+        let mut response = (self.status, json_response).into_response();
+        if let Some(retry_after) = self.retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, retry_after.into());
+        }
+
+        response
     }
 }
 
@@ -1,2 +1,10 @@
+pub mod admin;
+pub mod api_keys;
 pub mod auth;
+pub mod exports;
+pub mod mfa;
+pub mod oauth;
+pub mod roles;
 pub mod users;
+pub mod webauthn;
+pub mod well_known;
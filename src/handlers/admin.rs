@@ -0,0 +1,442 @@
+use std::{collections::HashSet, sync::Arc};
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, patch, put},
+    Extension, Json, Router,
+};
+use serde_json::json;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    db::{AuditEventExt, DbError, RoleExt, SessionExt, UserExt, UserUpdate},
+    dtos::{
+        AdminUpdateUserDto, FilterUserDto, Response, UpdateUserStatusDto, UserData, UserResponseDto,
+    },
+    error::{ErrorMessage, HttpError},
+    middleware::{guard::RequirePermission, AuthUser},
+    models::{AuditAction, Permission, User, UserStatus, ADMIN_ROLE},
+    AppState,
+};
+
+/// 用户管理路由 -- 挂载在 `/api/admin` 下，查看需要 `users:read`，修改需要 `users:write`，
+/// 修改角色还需要 `users:write_role`
+pub fn admin_handler() -> Router {
+    let read = || RequirePermission::new(Permission::ListUsers);
+    let write = || RequirePermission::new(Permission::ManageUsers);
+
+    Router::new()
+        .route(
+            "/users/{user_id}",
+            get(get_user)
+                .layer(read())
+                .merge(patch(update_user).delete(delete_user).layer(write())),
+        )
+        .route(
+            "/users/{user_id}/status",
+            put(update_user_status).layer(write()),
+        )
+}
+
+pub async fn get_user(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, HttpError> {
+    let user = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(user_not_found)?;
+
+    Ok(Json(UserResponseDto {
+        status: "success".to_string(),
+        data: UserData {
+            user: FilterUserDto::filter_user(&user),
+            recovery_codes_remaining: None,
+        },
+    }))
+}
+
+/// 修改用户的名称、邮箱、角色或邮箱验证状态 -- 不能将最后一个正常状态的管理员改为其他角色
+///
+/// 只能修改权限不超出自身权限的用户；不能修改自己的角色，也只能分配权限不超出自身权限的角色。
+/// 修改邮箱后新邮箱需要重新验证，该用户的令牌和会话全部失效
+pub async fn update_user(
+    Extension(app_state): Extension<Arc<AppState>>,
+    admin: AuthUser,
+    Path(user_id): Path<Uuid>,
+    Json(body): Json<AdminUpdateUserDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let target = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(user_not_found)?;
+
+    ensure_manageable(&app_state, &admin, &target).await?;
+
+    if let Some(role) = body.role.as_deref() {
+        ensure_assignable(&app_state, &admin, user_id, role).await?;
+    }
+
+    let update = UserUpdate {
+        name: body.name.as_deref().map(str::trim),
+        email: body.email.as_deref().map(str::trim),
+        role: body.role.as_deref(),
+        verified: body.verified,
+    };
+
+    let user = app_state
+        .db_client
+        .admin_update_user(user_id, update)
+        .await
+        .map_err(db_error)?;
+
+    record_audit_event(
+        &app_state,
+        &admin,
+        user.id,
+        AuditAction::UserUpdated,
+        json!({
+            "name": update.name,
+            "email": update.email,
+            "role": update.role,
+            "verified": update.verified,
+        }),
+    )
+    .await?;
+
+    tracing::info!("管理员 {} 修改了用户 {} 的资料", admin.id, user.email);
+
+    Ok(Json(UserResponseDto {
+        status: "success".to_string(),
+        data: UserData {
+            user: FilterUserDto::filter_user(&user),
+            recovery_codes_remaining: None,
+        },
+    }))
+}
+
+/// 修改账户状态 -- 改为非正常状态时同时吊销该用户的所有会话
+///
+/// 只能修改权限不超出自身权限的用户，不能将最后一个正常状态的管理员改为其他状态
+pub async fn update_user_status(
+    Extension(app_state): Extension<Arc<AppState>>,
+    admin: AuthUser,
+    Path(user_id): Path<Uuid>,
+    Json(body): Json<UpdateUserStatusDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let previous = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(user_not_found)?;
+
+    ensure_manageable(&app_state, &admin, &previous).await?;
+
+    let reason = body
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty());
+
+    let user = app_state
+        .db_client
+        .set_user_status(user_id, body.status, reason, body.suspended_until)
+        .await
+        .map_err(db_error)?;
+
+    if user.status != UserStatus::Active {
+        app_state
+            .db_client
+            .revoke_user_sessions(user.id)
+            .await
+            .map_err(|e| {
+                tracing::error!("吊销用户会话失败: {}", e);
+                HttpError::server_error(e.to_string())
+            })?;
+    }
+
+    record_audit_event(
+        &app_state,
+        &admin,
+        user.id,
+        AuditAction::StatusChanged,
+        json!({
+            "from": previous.status,
+            "to": user.status,
+            "reason": user.status_reason,
+            "suspendedUntil": user.suspended_until,
+        }),
+    )
+    .await?;
+
+    tracing::info!(
+        "管理员 {} 将用户 {} 的状态从 {} 修改为 {}",
+        admin.id,
+        user.email,
+        previous.status.to_str(),
+        user.status.to_str()
+    );
+
+    Ok(Json(UserResponseDto {
+        status: "success".to_string(),
+        data: UserData {
+            user: FilterUserDto::filter_user(&user),
+            recovery_codes_remaining: None,
+        },
+    }))
+}
+
+/// 删除用户 -- 只能删除权限不超出自身权限的用户，不能删除最后一个正常状态的管理员
+pub async fn delete_user(
+    Extension(app_state): Extension<Arc<AppState>>,
+    admin: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, HttpError> {
+    let user = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(user_not_found)?;
+
+    ensure_manageable(&app_state, &admin, &user).await?;
+
+    app_state
+        .db_client
+        .delete_user(user.id)
+        .await
+        .map_err(db_error)?;
+
+    record_audit_event(
+        &app_state,
+        &admin,
+        user.id,
+        AuditAction::UserDeleted,
+        json!({ "email": user.email }),
+    )
+    .await?;
+
+    tracing::info!("管理员 {} 删除了用户 {}", admin.id, user.email);
+
+    Ok(Json(Response {
+        status: "success",
+        message: "User deleted".to_string(),
+    }))
+}
+
+/// 记录管理员对用户的操作
+pub async fn record_audit_event(
+    app_state: &AppState,
+    admin: &AuthUser,
+    user_id: Uuid,
+    action: AuditAction,
+    details: serde_json::Value,
+) -> Result<(), HttpError> {
+    app_state
+        .db_client
+        .record_audit_event(Some(admin.id), Some(user_id), action, details)
+        .await
+        .map_err(|e| {
+            tracing::error!("记录审计事件失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })
+}
+
+/// 检查调用者能否分配角色 -- 防止通过修改角色提升自己或他人的权限
+async fn ensure_assignable(
+    app_state: &AppState,
+    admin: &AuthUser,
+    user_id: Uuid,
+    role: &str,
+) -> Result<(), HttpError> {
+    if !admin.has_permission(Permission::AssignRoles) || user_id == admin.id {
+        return Err(permission_denied());
+    }
+
+    ensure_covers_role(app_state, admin, role).await
+}
+
+/// 检查调用者能否管理目标用户 -- 防止低权限的操作者修改、封禁或删除权限更高的用户
+async fn ensure_manageable(
+    app_state: &AppState,
+    admin: &AuthUser,
+    target: &User,
+) -> Result<(), HttpError> {
+    ensure_covers_role(app_state, admin, &target.role).await
+}
+
+/// 检查调用者的权限是否覆盖角色 `role` 的全部权限
+async fn ensure_covers_role(
+    app_state: &AppState,
+    admin: &AuthUser,
+    role: &str,
+) -> Result<(), HttpError> {
+    // -- 直接查询数据库，不使用可能过期的权限缓存
+    let permissions = app_state
+        .db_client
+        .get_role_permissions(role)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !covers_role(&admin.role, &admin.permissions, role, &permissions) {
+        return Err(permission_denied());
+    }
+
+    Ok(())
+}
+
+/// 调用者是否可以管理角色 `role` -- `role` 的权限必须是调用者权限的子集，`admin` 角色只有管理员可以管理
+fn covers_role(
+    actor_role: &str,
+    actor_permissions: &HashSet<String>,
+    role: &str,
+    permissions: &[String],
+) -> bool {
+    (role != ADMIN_ROLE || actor_role == ADMIN_ROLE)
+        && permissions.iter().all(|p| actor_permissions.contains(p))
+}
+
+fn permission_denied() -> HttpError {
+    HttpError::new(
+        ErrorMessage::PermissionDenied.to_string(),
+        StatusCode::FORBIDDEN,
+    )
+}
+
+fn user_not_found() -> HttpError {
+    HttpError::new("User not found", StatusCode::NOT_FOUND)
+}
+
+/// 将修改用户时的数据库错误转换为 HTTP 错误
+pub fn db_error(error: DbError) -> HttpError {
+    match error {
+        DbError::UserNotFound => user_not_found(),
+        DbError::EmailExists => {
+            HttpError::unique_constraint_violation(ErrorMessage::EmailExist.to_string())
+        }
+        DbError::RoleNotFound => HttpError::bad_request(ErrorMessage::RoleNotFound.to_string()),
+        DbError::LastAdmin => {
+            HttpError::new(ErrorMessage::LastAdmin.to_string(), StatusCode::CONFLICT)
+        }
+        e => {
+            tracing::error!("修改用户失败: {}", e);
+            HttpError::server_error(e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::USER_ROLE;
+
+    fn permissions(permissions: &[Permission]) -> Vec<String> {
+        permissions.iter().map(|p| p.to_str().to_string()).collect()
+    }
+
+    fn permission_set(permissions: &[Permission]) -> HashSet<String> {
+        self::permissions(permissions).into_iter().collect()
+    }
+
+    const ADMIN_PERMISSIONS: [Permission; 6] = [
+        Permission::ManageOwnAccount,
+        Permission::ListUsers,
+        Permission::ManageUsers,
+        Permission::AssignRoles,
+        Permission::ReadRoles,
+        Permission::ManageRoles,
+    ];
+
+    const OPERATOR_PERMISSIONS: [Permission; 3] = [
+        Permission::ManageOwnAccount,
+        Permission::ListUsers,
+        Permission::ManageUsers,
+    ];
+
+    #[test]
+    fn admin_can_manage_every_role() {
+        let actor = permission_set(&ADMIN_PERMISSIONS);
+
+        for (role, role_permissions) in [
+            (ADMIN_ROLE, &ADMIN_PERMISSIONS[..]),
+            (USER_ROLE, &[Permission::ManageOwnAccount][..]),
+            ("operator", &OPERATOR_PERMISSIONS[..]),
+        ] {
+            assert!(
+                covers_role(ADMIN_ROLE, &actor, role, &permissions(role_permissions)),
+                "{}",
+                role
+            );
+        }
+    }
+
+    #[test]
+    fn operator_cannot_manage_admins() {
+        // -- 修改资料、修改状态和删除用户都按目标用户的角色检查
+        let actor = permission_set(&OPERATOR_PERMISSIONS);
+
+        assert!(!covers_role(
+            "operator",
+            &actor,
+            ADMIN_ROLE,
+            &permissions(&ADMIN_PERMISSIONS)
+        ));
+    }
+
+    #[test]
+    fn admin_role_requires_admin_even_with_all_permissions() {
+        let actor = permission_set(&ADMIN_PERMISSIONS);
+
+        assert!(!covers_role(
+            "superuser",
+            &actor,
+            ADMIN_ROLE,
+            &permissions(&ADMIN_PERMISSIONS)
+        ));
+    }
+
+    #[test]
+    fn operator_cannot_manage_higher_privileged_role() {
+        let actor = permission_set(&OPERATOR_PERMISSIONS);
+        let role_manager = permissions(&[Permission::ManageOwnAccount, Permission::ManageRoles]);
+
+        assert!(!covers_role(
+            "operator",
+            &actor,
+            "role_manager",
+            &role_manager
+        ));
+    }
+
+    #[test]
+    fn operator_can_manage_equal_or_lower_roles() {
+        let actor = permission_set(&OPERATOR_PERMISSIONS);
+
+        assert!(covers_role(
+            "operator",
+            &actor,
+            USER_ROLE,
+            &permissions(&[Permission::ManageOwnAccount])
+        ));
+        assert!(covers_role(
+            "operator",
+            &actor,
+            "operator",
+            &permissions(&OPERATOR_PERMISSIONS)
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    db::ApiKeyExt,
+    dtos::{
+        ApiKeyCreatedResponseDto, ApiKeyListResponseDto, CreateApiKeyDto, FilterApiKeyDto, Response,
+    },
+    error::HttpError,
+    middleware::AuthUser,
+    utils::token,
+    AppState,
+};
+
+/// API 密钥管理路由 -- 挂载在 `/api/users/api-keys` 下，只允许登录会话访问
+pub fn api_keys_handler() -> Router {
+    Router::new()
+        .route("/", get(get_api_keys).post(create_api_key))
+        .route("/{id}", delete(revoke_api_key))
+}
+
+/// 未指定有效期时 API 密钥的默认有效期（天）
+const DEFAULT_API_KEY_EXPIRY_DAYS: i64 = 90;
+
+/// 创建 API 密钥 -- 完整密钥只在响应中返回这一次，数据库只保存其哈希
+pub async fn create_api_key(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<CreateApiKeyDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = &auth.user(&app_state).await?;
+
+    let mut scopes: Vec<String> = body
+        .scopes
+        .iter()
+        .map(|scope| scope.to_str().to_string())
+        .collect();
+    scopes.sort();
+    scopes.dedup();
+
+    let expires_at =
+        Utc::now() + Duration::days(body.expires_in_days.unwrap_or(DEFAULT_API_KEY_EXPIRY_DAYS));
+    let (api_key, prefix) = token::generate_api_key();
+
+    let saved = app_state
+        .db_client
+        .save_api_key(
+            user.id,
+            body.name.trim(),
+            &prefix,
+            &token::hash_opaque_token(&api_key),
+            &scopes,
+            expires_at,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("保存 API 密钥失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    tracing::info!("用户 {} 创建了 API 密钥 {}", user.email, saved.prefix);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ApiKeyCreatedResponseDto {
+            status: "success".to_string(),
+            api_key,
+            key: FilterApiKeyDto::filter_api_key(&saved),
+        }),
+    ))
+}
+
+/// 获取当前用户的 API 密钥列表 -- 只返回前缀，不返回完整密钥
+pub async fn get_api_keys(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<impl IntoResponse, HttpError> {
+    let api_keys = app_state
+        .db_client
+        .get_api_keys(auth.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(ApiKeyListResponseDto {
+        status: "success".to_string(),
+        api_keys: FilterApiKeyDto::filter_api_keys(&api_keys),
+    }))
+}
+
+/// 吊销 API 密钥 -- 吊销后立即失效
+pub async fn revoke_api_key(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, HttpError> {
+    let revoked = app_state
+        .db_client
+        .revoke_api_key(auth.id, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("吊销 API 密钥失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    if !revoked {
+        return Err(HttpError::new("API key not found", StatusCode::NOT_FOUND));
+    }
+
+    tracing::info!("用户 {} 吊销了 API 密钥 {}", auth.id, id);
+
+    Ok(Json(Response {
+        status: "success",
+        message: "API key revoked".to_string(),
+    }))
+}
@@ -1,25 +1,44 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
-    extract::Query,
+    body::Bytes,
+    extract::{ConnectInfo, Query},
     http::{header, HeaderMap, StatusCode},
-    response::{IntoResponse, Redirect},
+    middleware,
+    response::{IntoResponse, Redirect, Response as AxumResponse},
     routing::{get, post},
     Extension, Json, Router,
 };
-use axum_extra::extract::cookie::Cookie;
-use chrono::{Duration, FixedOffset, Utc};
+use axum_extra::extract::cookie::{Cookie, CookieJar};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use serde_json::json;
+use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    db::UserExt,
+    db::{AuditEventExt, LoginAttemptExt, SessionExt, UserExt},
     dtos::{
-        ForgotPasswordRequestDto, LoginUserDto, RegisterUserDto, ResendVerificationDto,
-        ResetPasswordRequestDto, Response, UserLoginResponseDto, VerifyEmailQueryDto,
+        ForgotPasswordRequestDto, LoginUserDto, MagicLinkQueryDto, MagicLinkRequestDto,
+        MfaRequiredResponseDto, MfaVerifyDto, NewPasswordDto, PasswordChangeRequiredResponseDto,
+        RefreshTokenDto, RegisterUserDto, ResendVerificationDto, ResetPasswordRequestDto, Response,
+        UnlockAccountQueryDto, UserLoginResponseDto, VerifyEmailQueryDto,
     },
     error::{ErrorMessage, HttpError},
-    mail::mails::{send_forgot_password_email, send_verification_email, send_welcome_email},
-    utils::{password, token},
+    handlers::{
+        mfa::{check_recovery_code, check_totp_code},
+        oauth::oauth_handler,
+        webauthn::webauthn_handler,
+    },
+    mail::mails::{
+        send_account_locked_email, send_forgot_password_email, send_magic_link_email,
+        send_registration_attempt_email, send_verification_email, send_welcome_email,
+    },
+    middleware::{auth, ensure_active, extract_access_token, require_session, AuthUser},
+    models::{AuditAction, User, UserTokenPurpose},
+    utils::{
+        password,
+        token::{self, TokenClaims, TokenScope},
+    },
     AppState,
 };
 
@@ -32,6 +51,432 @@ pub fn auth_handler() -> Router {
         .route("/resend-verification", post(resend_verification_email))
         .route("/forgot-password", post(forgot_password))
         .route("/reset-password", post(reset_password))
+        .route("/magic-link", post(request_magic_link))
+        .route("/magic-link/verify", get(verify_magic_link))
+        .route("/unlock", get(unlock_account))
+        .route("/mfa/verify", post(verify_mfa))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+        .route(
+            "/logout-all",
+            post(logout_all)
+                .layer(middleware::from_fn(require_session))
+                .layer(middleware::from_fn(auth)),
+        )
+        .nest("/webauthn", webauthn_handler())
+        .nest("/oauth", oauth_handler())
+}
+
+/// 刷新令牌 cookie 名称
+const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
+
+/// MFA 令牌有效期（分钟）
+const MFA_TOKEN_MAXAGE: i64 = 5;
+
+/// 修改密码令牌有效期（分钟）
+const PASSWORD_CHANGE_TOKEN_MAXAGE: i64 = 10;
+
+/// 计算邮件令牌的摘要 -- 数据库中只保存使用服务端密钥计算的 HMAC
+pub fn hash_user_token(app_state: &AppState, user_token: &str) -> String {
+    token::hash_user_token(user_token, app_state.env.token_hmac_secret.as_bytes())
+}
+
+/// 使用当前配置重新哈希已校验通过的密码 -- 失败只记录日志，不影响本次登录
+async fn upgrade_password_hash(app_state: &AppState, user: &User, plain_password: &str) {
+    let new_hash = match password::hash(plain_password, &app_state.env.password_hash) {
+        Ok(new_hash) => new_hash,
+        Err(e) => {
+            tracing::error!("重新哈希用户 {} 的密码失败: {}", user.id, e);
+            return;
+        }
+    };
+
+    match app_state
+        .db_client
+        .upgrade_password_hash(user.id, &user.password, &new_hash)
+        .await
+    {
+        Ok(_) => tracing::info!("已升级用户 {} 的密码哈希", user.id),
+        Err(e) => tracing::error!("保存用户 {} 的新密码哈希失败: {}", user.id, e),
+    }
+}
+
+/// 检查新密码是否与最近使用过的密码相同
+///
+/// 最近使用过的密码包括当前密码和密码历史中的旧密码，共 `PASSWORD_HISTORY_SIZE` 个。
+pub async fn ensure_password_not_reused(
+    app_state: &AppState,
+    user: &User,
+    new_password: &str,
+) -> Result<(), HttpError> {
+    let history_size = app_state.env.password_policy.history_size;
+    if history_size == 0 {
+        return Ok(());
+    }
+
+    let password_history = app_state
+        .db_client
+        .get_password_history(user.id, history_size - 1)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    for hashed_password in std::iter::once(&user.password).chain(&password_history) {
+        let reused = password::compare(new_password, hashed_password, &app_state.env.password_hash)
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        if reused {
+            tracing::warn!("用户 {} 尝试重复使用最近的密码", user.id);
+            return Err(HttpError::bad_request(
+                ErrorMessage::PasswordReused(history_size).to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// 创建访问令牌 -- 短期有效的 JWT，携带用户当前的角色、令牌版本和所属会话
+fn create_access_token(
+    app_state: &AppState,
+    user: &User,
+    session_id: Uuid,
+) -> Result<String, HttpError> {
+    token::create_token(
+        user,
+        Some(session_id),
+        TokenScope::Access,
+        &app_state.jwt_keys,
+        app_state.env.jwt_maxage,
+    )
+    .map_err(|e| {
+        tracing::error!("创建 JWT token 失败: {}", e);
+        HttpError::server_error(e.to_string())
+    })
+}
+
+/// 创建新的会话族 -- 生成刷新令牌并只在数据库中保存其哈希，返回刷新令牌和会话族ID
+async fn start_session(app_state: &AppState, user_id: Uuid) -> Result<(String, Uuid), HttpError> {
+    let refresh_token = token::generate_opaque_token();
+    let family_id = Uuid::new_v4();
+    let expires_at = Utc::now() + Duration::minutes(app_state.env.refresh_token_maxage);
+
+    app_state
+        .db_client
+        .save_session(
+            user_id,
+            family_id,
+            &token::hash_opaque_token(&refresh_token),
+            expires_at,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("创建会话失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    Ok((refresh_token, family_id))
+}
+
+/// 构建认证 cookie -- 访问令牌对全站可见，刷新令牌只发送给认证接口
+fn auth_cookie_headers(app_state: &AppState, access_token: &str, refresh_token: &str) -> HeaderMap {
+    let cookie_duration = time::Duration::minutes(app_state.env.jwt_maxage);
+    let cookie = Cookie::build(("token", access_token.to_owned()))
+        .path("/")
+        .max_age(cookie_duration)
+        .http_only(true)
+        .build();
+
+    let refresh_cookie_duration = time::Duration::minutes(app_state.env.refresh_token_maxage);
+    let refresh_cookie = Cookie::build((REFRESH_TOKEN_COOKIE, refresh_token.to_owned()))
+        .path("/api/auth")
+        .max_age(refresh_cookie_duration)
+        .http_only(true)
+        .build();
+
+    let mut headers = HeaderMap::new();
+    headers.append(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+    headers.append(
+        header::SET_COOKIE,
+        refresh_cookie.to_string().parse().unwrap(),
+    );
+
+    headers
+}
+
+/// 构建登录成功响应 -- 签发访问令牌与刷新令牌，并同时写入 cookie 和响应体
+///
+/// 必须先修改密码的用户只会获得修改密码令牌，不会创建会话。
+pub async fn login_response(app_state: &AppState, user: &User) -> Result<AxumResponse, HttpError> {
+    ensure_active(user)?;
+
+    if user.deletion_expired(app_state.env.account_deletion_grace_days) {
+        return Err(HttpError::unauthorized(
+            ErrorMessage::UserNoLongerExist.to_string(),
+        ));
+    }
+
+    restore_deleted_account(app_state, user).await?;
+
+    if user.password_change_required(app_state.env.password_policy.max_age_days) {
+        return password_change_required_response(app_state, user);
+    }
+
+    let (refresh_token, session_id) = start_session(app_state, user.id).await?;
+    let token = create_access_token(app_state, user, session_id)?;
+
+    let headers = auth_cookie_headers(app_state, &token, &refresh_token);
+
+    let mut response = Json(UserLoginResponseDto {
+        status: "success".to_string(),
+        token,
+        refresh_token,
+    })
+    .into_response();
+    response.headers_mut().extend(headers);
+
+    Ok(response)
+}
+
+/// 构建登录成功的重定向响应 -- 写入认证 cookie 后重定向到前端
+///
+/// 必须先修改密码的用户跳转到前端修改密码页，修改密码令牌放在 URL 片段中。
+pub async fn login_redirect(app_state: &AppState, user: &User) -> Result<AxumResponse, HttpError> {
+    if !user.is_active() {
+        let error = format!("account_{}", user.status.to_str());
+        return Ok(login_error_redirect(app_state, &error));
+    }
+
+    if user.deletion_expired(app_state.env.account_deletion_grace_days) {
+        return Ok(login_error_redirect(app_state, "account_deleted"));
+    }
+
+    restore_deleted_account(app_state, user).await?;
+
+    if user.password_change_required(app_state.env.password_policy.max_age_days) {
+        let password_change_token = create_password_change_token(app_state, user)?;
+        let url = format!(
+            "{}/change-password#password_change_token={}",
+            app_state.env.frontend_url.trim_end_matches('/'),
+            password_change_token
+        );
+        return Ok(Redirect::to(&url).into_response());
+    }
+
+    let (refresh_token, session_id) = start_session(app_state, user.id).await?;
+    let token = create_access_token(app_state, user, session_id)?;
+    let headers = auth_cookie_headers(app_state, &token, &refresh_token);
+
+    let mut response = Redirect::to(&app_state.env.frontend_url).into_response();
+    response.headers_mut().extend(headers);
+
+    Ok(response)
+}
+
+/// 宽限期内重新登录时撤销账户的删除申请 -- 在所有登录方式完成认证后调用，调用前需确认宽限期未过
+async fn restore_deleted_account(app_state: &AppState, user: &User) -> Result<(), HttpError> {
+    if !user.is_pending_deletion() {
+        return Ok(());
+    }
+
+    let restored = app_state
+        .db_client
+        .restore_deleted_user(user.id, app_state.env.account_deletion_grace_days)
+        .await
+        .map_err(|e| {
+            tracing::error!("恢复账户失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    if restored {
+        app_state
+            .db_client
+            .record_audit_event(
+                Some(user.id),
+                Some(user.id),
+                AuditAction::AccountRestored,
+                json!({}),
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("记录审计事件失败: {}", e);
+                HttpError::server_error(e.to_string())
+            })?;
+
+        tracing::info!("用户 {} 重新登录，已撤销账户删除申请", user.email);
+    }
+
+    Ok(())
+}
+
+/// 第一因素通过后的重定向 -- 开启两步验证的用户跳转到前端两步验证页，否则直接登录
+///
+/// 用于浏览器跳转类的登录方式（第三方登录、邮件登录链接），MFA 令牌放在 URL 片段中，
+/// 不会出现在服务端日志和 Referer 里。
+pub async fn first_factor_redirect(
+    app_state: &AppState,
+    user: &User,
+) -> Result<AxumResponse, HttpError> {
+    if user.mfa_enabled {
+        let mfa_token = create_mfa_token(app_state, user)?;
+        let url = format!(
+            "{}/login/mfa#mfa_token={}",
+            app_state.env.frontend_url.trim_end_matches('/'),
+            mfa_token
+        );
+        return Ok(Redirect::to(&url).into_response());
+    }
+
+    login_redirect(app_state, user).await
+}
+
+/// 重定向回前端登录页并携带错误码
+pub fn login_error_redirect(app_state: &AppState, error: &str) -> AxumResponse {
+    let url = format!(
+        "{}/login?error={}",
+        app_state.env.frontend_url.trim_end_matches('/'),
+        error
+    );
+    Redirect::to(&url).into_response()
+}
+
+/// 创建 MFA 令牌 -- 只能用于完成两步验证的短期令牌
+pub fn create_mfa_token(app_state: &AppState, user: &User) -> Result<String, HttpError> {
+    token::create_token(
+        user,
+        None,
+        TokenScope::MfaPending,
+        &app_state.jwt_keys,
+        MFA_TOKEN_MAXAGE,
+    )
+    .map_err(|e| HttpError::server_error(e.to_string()))
+}
+
+/// 构建需要第二因素的响应 -- 返回 MFA 令牌，完成两步验证后才签发访问令牌
+pub fn mfa_required_response(app_state: &AppState, user: &User) -> Result<AxumResponse, HttpError> {
+    let mfa_token = create_mfa_token(app_state, user)?;
+
+    Ok(Json(MfaRequiredResponseDto {
+        status: "mfa_required".to_string(),
+        mfa_token,
+    })
+    .into_response())
+}
+
+/// 创建修改密码令牌 -- 只能用于调用修改密码接口的短期令牌
+fn create_password_change_token(app_state: &AppState, user: &User) -> Result<String, HttpError> {
+    token::create_token(
+        user,
+        None,
+        TokenScope::PasswordChange,
+        &app_state.jwt_keys,
+        PASSWORD_CHANGE_TOKEN_MAXAGE,
+    )
+    .map_err(|e| HttpError::server_error(e.to_string()))
+}
+
+/// 构建需要修改密码的响应 -- 返回修改密码令牌，修改密码后需要使用新密码重新登录
+fn password_change_required_response(
+    app_state: &AppState,
+    user: &User,
+) -> Result<AxumResponse, HttpError> {
+    tracing::info!("用户 {} 需要修改密码后才能登录", user.email);
+
+    let token = create_password_change_token(app_state, user)?;
+
+    Ok(Json(PasswordChangeRequiredResponseDto {
+        status: "password_change_required".to_string(),
+        token,
+    })
+    .into_response())
+}
+
+/// 校验 MFA 令牌但不消费 -- 用于发起通行密钥第二因素挑战
+pub async fn check_mfa_token(app_state: &AppState, mfa_token: &str) -> Result<User, HttpError> {
+    validate_mfa_token(app_state, mfa_token)
+        .await
+        .map(|(user, _)| user)
+}
+
+/// 校验并消费 MFA 令牌 -- 令牌只能使用一次，成功后返回对应的用户
+pub async fn consume_mfa_token(app_state: &AppState, mfa_token: &str) -> Result<User, HttpError> {
+    let (user, claims) = validate_mfa_token(app_state, mfa_token).await?;
+    let jti = Uuid::parse_str(&claims.jti)
+        .map_err(|_| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
+
+    // -- 立即吊销 MFA 令牌，防止同一令牌被用于多次猜测验证码
+    let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+    app_state
+        .db_client
+        .revoke_token(jti, user.id, expires_at)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(user)
+}
+
+/// 校验 MFA 令牌的作用域、黑名单与令牌版本，返回对应的用户与令牌声明
+async fn validate_mfa_token(
+    app_state: &AppState,
+    mfa_token: &str,
+) -> Result<(User, TokenClaims), HttpError> {
+    let invalid_token = || HttpError::unauthorized(ErrorMessage::InvalidToken.to_string());
+
+    let claims =
+        token::decode_token(mfa_token, &app_state.jwt_keys).map_err(|_| invalid_token())?;
+
+    if claims.scope != TokenScope::MfaPending {
+        return Err(invalid_token());
+    }
+
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| invalid_token())?;
+    let jti = Uuid::parse_str(&claims.jti).map_err(|_| invalid_token())?;
+
+    let revoked = app_state
+        .db_client
+        .is_token_revoked(jti)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if revoked {
+        return Err(invalid_token());
+    }
+
+    let user = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+
+    if claims.ver != user.token_version {
+        return Err(invalid_token());
+    }
+
+    Ok((user, claims))
+}
+
+/// 构建清除认证 cookie 的响应头 -- 退出登录和申请删除账户时使用
+pub fn clear_auth_cookie_headers() -> HeaderMap {
+    let cookie = Cookie::build(("token", ""))
+        .path("/")
+        .max_age(time::Duration::ZERO)
+        .http_only(true)
+        .build();
+
+    let refresh_cookie = Cookie::build((REFRESH_TOKEN_COOKIE, ""))
+        .path("/api/auth")
+        .max_age(time::Duration::ZERO)
+        .http_only(true)
+        .build();
+
+    let mut headers = HeaderMap::new();
+    headers.append(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+    headers.append(
+        header::SET_COOKIE,
+        refresh_cookie.to_string().parse().unwrap(),
+    );
+
+    headers
 }
 
 /// 处理用户注册请求 -- 创建新用户并发送验证邮件
@@ -46,28 +491,49 @@ pub fn auth_handler() -> Router {
 ///   - `BadRequest` -- 请求参数验证失败
 ///   - `UniqueViolation` -- 邮箱已存在
 ///   - `ServerError` -- 服务器内部错误
+///
+/// 隐私模式下邮箱已注册时同样返回注册成功，并改为向该邮箱发送提醒邮件。
 pub async fn register(
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<RegisterUserDto>,
 ) -> Result<impl IntoResponse, HttpError> {
-    // -- 验证请求数据
-    body.validate()
+    // -- 验证请求数据，密码需满足密码策略且不能包含用户名和邮箱
+    body.validate_with_policy(&app_state.env.password_policy, &[&body.name, &body.email])
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
     // -- 检查邮箱是否已注册
     let user_exists = app_state
         .db_client
-        .get_user(None, None, Some(&body.email), None)
+        .get_user(None, None, Some(&body.email))
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    if user_exists.is_some() {
-        return Err(HttpError::bad_request("邮箱已被注册".to_string()));
+    if let Some(existing_user) = user_exists {
+        if !app_state.env.privacy_mode {
+            return Err(HttpError::bad_request("邮箱已被注册".to_string()));
+        }
+
+        // -- 隐私模式：同样执行一次密码哈希使响应耗时一致，并提醒邮箱所有者有人尝试注册
+        let _ = password::hash(&body.password, &app_state.env.password_hash);
+
+        tracing::warn!(
+            "隐私模式下收到已注册邮箱的注册请求: {}",
+            existing_user.email
+        );
+        tokio::spawn(async move {
+            if let Err(e) =
+                send_registration_attempt_email(&existing_user.email, &existing_user.name).await
+            {
+                tracing::error!("发送注册提醒邮件失败: {}", e);
+            }
+        });
+
+        return Ok(registration_accepted());
     }
 
-    // -- 生成验证 token，有效期设置为 30 分钟
-    let verification_token = uuid::Uuid::new_v4().to_string();
-    let token_expires_at = Utc::now() + Duration::minutes(30);
+    // -- 生成验证 token，数据库只保存其哈希
+    let verification_token = token::generate_opaque_token();
+    let token_expires_at = Utc::now() + UserTokenPurpose::VerifyEmail.ttl();
 
     // -- 打印时间信息以便调试
     tracing::info!(
@@ -77,8 +543,8 @@ pub async fn register(
     );
 
     // -- 加密密码
-    let hash_password =
-        password::hash(&body.password).map_err(|e| HttpError::server_error(e.to_string()))?;
+    let hash_password = password::hash(&body.password, &app_state.env.password_hash)
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
 
     // -- 保存用户信息
     let result = app_state
@@ -87,7 +553,7 @@ pub async fn register(
             &body.name,
             &body.email,
             &hash_password,
-            &verification_token,
+            &hash_user_token(&app_state, &verification_token),
             token_expires_at,
         )
         .await;
@@ -109,18 +575,14 @@ pub async fn register(
             });
 
             // -- 返回注册成功响应
-            Ok((
-                StatusCode::CREATED,
-                Json(Response {
-                    status: "success",
-                    message: "注册成功，请在 30 分钟内完成邮箱验证".to_string(),
-                }),
-            ))
+            Ok(registration_accepted())
         }
         // -- 处理数据库错误
         Err(sqlx::Error::Database(db_err)) => {
-            // -- 处理唯一约束违反（邮箱已存在）
-            if db_err.is_unique_violation() {
+            // -- 处理唯一约束违反（邮箱已存在），隐私模式下同样返回注册成功
+            if db_err.is_unique_violation() && app_state.env.privacy_mode {
+                Ok(registration_accepted())
+            } else if db_err.is_unique_violation() {
                 Err(HttpError::unique_constraint_violation(
                     ErrorMessage::EmailExist.to_string(),
                 ))
@@ -134,89 +596,249 @@ pub async fn register(
     }
 }
 
+/// 注册成功响应
+fn registration_accepted() -> (StatusCode, Json<Response>) {
+    (
+        StatusCode::CREATED,
+        Json(Response {
+            status: "success",
+            message: "注册成功，请在 30 分钟内完成邮箱验证".to_string(),
+        }),
+    )
+}
+
 /// 处理用户登录请求 -- 验证用户身份并生成访问令牌
 ///
+/// # 暴力破解防护
+/// - 同一来源 IP 在锁定时间窗口内失败次数过多时，拒绝该 IP 的所有登录请求
+/// - 同一账户连续密码错误达到上限时临时锁定账户，并向用户发送解锁链接
+/// - 每次失败后按账户的连续失败次数递增响应延迟
+///
 /// # 参数
 /// - `app_state` -- 应用程序状态，包含数据库连接等共享资源
+/// - `addr` -- 客户端地址
 /// - `body` -- 登录请求体，包含邮箱和密码
 ///
 /// # 返回
 /// - `Ok(Response)` -- 登录成功，返回访问令牌和用户信息
 /// - `Err(HttpError)` -- 登录失败，返回错误信息
-///   - `BadRequest` -- 请求参数验证失败
-///   - `Unauthorized` -- 邮箱或密码错误
+///   - `BadRequest` -- 请求参数验证失败，或邮箱、密码错误
+///   - `Locked` -- 账户已被临时锁定
+///   - `TooManyRequests` -- 来源 IP 失败次数过多
 ///   - `ServerError` -- 服务器内部错误
-///   - `NotFound` -- 用户未找到
-///   - `Forbidden` -- 账户未验证
 pub async fn login(
     Extension(app_state): Extension<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Json(body): Json<LoginUserDto>,
 ) -> Result<impl IntoResponse, HttpError> {
     body.validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
+    let ip_address = addr.ip().to_string();
+
+    // -- 检查来源 IP 的失败次数
+    let window_start = Utc::now() - Duration::minutes(app_state.env.login_lockout_minutes);
+    let ip_failures = app_state
+        .db_client
+        .count_failed_login_attempts_by_ip(&ip_address, window_start)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if ip_failures >= app_state.env.login_ip_max_attempts {
+        tracing::warn!("来源 IP {} 登录失败次数过多", ip_address);
+        return Err(HttpError::too_many_requests(
+            ErrorMessage::TooManyLoginAttempts.to_string(),
+            std::time::Duration::from_secs(app_state.env.login_lockout_minutes as u64 * 60),
+        ));
+    }
+
     let result = app_state
         .db_client
-        .get_user(None, None, Some(&body.email), None)
+        .get_user(None, None, Some(&body.email))
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    let user = result.ok_or(HttpError::bad_request(
-        ErrorMessage::WrongCredentials.to_string(),
-    ))?;
+    let Some(user) = result else {
+        if app_state.env.privacy_mode {
+            password::dummy_compare(&body.password, &app_state.env.password_hash);
+        }
+        record_login_attempt(&app_state, None, &body.email, &ip_address, false).await?;
+        failed_login_delay(0).await;
+        return Err(HttpError::bad_request(
+            ErrorMessage::WrongCredentials.to_string(),
+        ));
+    };
+
+    // -- 锁定期内不校验密码，避免锁定期间继续猜测
+    if user.is_locked() {
+        record_login_attempt(&app_state, Some(user.id), &body.email, &ip_address, false).await?;
+        return Err(account_locked_error());
+    }
+
+    let password_matched =
+        password::compare(&body.password, &user.password, &app_state.env.password_hash)
+            .unwrap_or(false);
+
+    if !password_matched {
+        record_login_attempt(&app_state, Some(user.id), &body.email, &ip_address, false).await?;
+        return Err(record_failed_login(&app_state, &user).await);
+    }
+
+    record_login_attempt(&app_state, Some(user.id), &body.email, &ip_address, true).await?;
+
+    // -- 密码正确后才提示账户状态，避免向他人透露
+    ensure_active(&user)?;
+
+    // -- 哈希参数已过时或为旧系统的 bcrypt 哈希时，使用当前配置重新哈希
+    if password::needs_rehash(&user.password, &app_state.env.password_hash) {
+        upgrade_password_hash(&app_state, &user, &body.password).await;
+    }
+
+    if user.failed_login_attempts > 0 || user.locked_until.is_some() {
+        app_state
+            .db_client
+            .unlock_user(user.id)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+    }
 
-    let password_matched = password::compare(&body.password, &user.password)
-        .map_err(|_| HttpError::bad_request(ErrorMessage::WrongCredentials.to_string()))?;
+    // -- 开启两步验证的用户先获得短期 MFA 令牌，完成第二因素后才签发访问令牌
+    if user.mfa_enabled {
+        return mfa_required_response(&app_state, &user);
+    }
+
+    login_response(&app_state, &user).await
+}
+
+/// 失败登录的基础延迟（毫秒），每次连续失败后翻倍
+const LOGIN_FAILURE_BASE_DELAY_MS: u64 = 200;
 
-    if password_matched {
-        let token = token::create_token(
-            &user.id.to_string(),
-            app_state.env.jwt_secret.as_bytes(),
-            app_state.env.jwt_maxage,
+/// 失败登录的最大延迟倍数（2 的幂）
+const LOGIN_FAILURE_MAX_DELAY_SHIFT: i32 = 4;
+
+/// 失败登录后的递增延迟 -- 200ms、400ms、800ms ... 最多 3.2 秒
+async fn failed_login_delay(failed_attempts: i32) {
+    let shift = failed_attempts.clamp(0, LOGIN_FAILURE_MAX_DELAY_SHIFT) as u32;
+    tokio::time::sleep(std::time::Duration::from_millis(
+        LOGIN_FAILURE_BASE_DELAY_MS << shift,
+    ))
+    .await;
+}
+
+fn account_locked_error() -> HttpError {
+    HttpError::new(ErrorMessage::AccountLocked.to_string(), StatusCode::LOCKED)
+}
+
+/// 记录登录尝试
+async fn record_login_attempt(
+    app_state: &AppState,
+    user_id: Option<Uuid>,
+    email: &str,
+    ip_address: &str,
+    success: bool,
+) -> Result<(), HttpError> {
+    app_state
+        .db_client
+        .record_login_attempt(user_id, email, ip_address, success)
+        .await
+        .map_err(|e| {
+            tracing::error!("记录登录尝试失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })
+}
+
+/// 记录一次密码错误 -- 达到上限时锁定账户并发送解锁邮件，返回应响应给客户端的错误
+async fn record_failed_login(app_state: &AppState, user: &User) -> HttpError {
+    let lockout = Duration::minutes(app_state.env.login_lockout_minutes);
+
+    let updated = match app_state
+        .db_client
+        .record_failed_login(
+            user.id,
+            app_state.env.login_max_attempts,
+            Utc::now() + lockout,
         )
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+        .await
+    {
+        Ok(updated) => updated,
+        Err(e) => return HttpError::server_error(e.to_string()),
+    };
 
-        let cookie_duration = time::Duration::minutes(app_state.env.jwt_maxage * 60);
-        let cookie = Cookie::build(("token", token.clone()))
-            .path("/")
-            .max_age(cookie_duration)
-            .http_only(true)
-            .build();
+    if !updated.is_locked() {
+        failed_login_delay(updated.failed_login_attempts).await;
+        return HttpError::bad_request(ErrorMessage::WrongCredentials.to_string());
+    }
 
-        let response = axum::response::Json(UserLoginResponseDto {
-            status: "success".to_string(),
-            token,
-        });
+    tracing::warn!("用户 {} 连续登录失败，账户已临时锁定", user.email);
 
-        let mut headers = HeaderMap::new();
+    let unlock_token = token::generate_opaque_token();
+    let expires_at = Utc::now() + UserTokenPurpose::UnlockAccount.ttl();
 
-        headers.append(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+    if let Err(e) = app_state
+        .db_client
+        .save_user_token(
+            user.id,
+            UserTokenPurpose::UnlockAccount,
+            &hash_user_token(app_state, &unlock_token),
+            expires_at,
+        )
+        .await
+    {
+        tracing::error!("保存解锁令牌失败: {}", e);
+        return account_locked_error();
+    }
 
-        let mut response = response.into_response();
-        response.headers_mut().extend(headers);
+    let email = user.email.clone();
+    let name = user.name.clone();
+    let lockout_minutes = app_state.env.login_lockout_minutes;
+    tokio::spawn(async move {
+        match send_account_locked_email(&email, &name, &unlock_token, lockout_minutes).await {
+            Ok(_) => tracing::info!("成功发送账户锁定邮件给用户: {}", email),
+            Err(e) => tracing::error!("发送账户锁定邮件失败: {}", e),
+        }
+    });
 
-        Ok(response)
-    } else {
-        Err(HttpError::bad_request(
-            ErrorMessage::WrongCredentials.to_string(),
-        ))
+    account_locked_error()
+}
+
+/// 处理两步验证请求 -- 使用登录时返回的 MFA 令牌和 TOTP 验证码（或恢复码）完成登录
+///
+/// # 返回
+/// - `Ok(Response)` -- 验证成功，返回与登录接口相同的访问令牌和 cookie
+/// - `Err(HttpError)` -- 验证失败
+///   - `Unauthorized` -- MFA 令牌无效、已使用或验证码错误
+pub async fn verify_mfa(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<MfaVerifyDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = consume_mfa_token(&app_state, &body.mfa_token).await?;
+
+    // -- 验证码与恢复码二选一
+    match (&body.code, &body.recovery_code) {
+        (Some(code), None) => check_totp_code(&app_state, &user, code).await?,
+        (None, Some(recovery_code)) => {
+            check_recovery_code(&app_state, &user, recovery_code).await?
+        }
+        _ => {
+            return Err(HttpError::bad_request(
+                "Provide either code or recovery_code".to_string(),
+            ))
+        }
     }
+
+    tracing::info!("用户 {} 完成两步验证", user.email);
+    login_response(&app_state, &user).await
 }
 
 /// 处理邮箱验证请求 -- 验证用户的邮箱验证 token
 ///
 /// # 验证流程
 /// 1. 验证请求参数格式
-/// 2. 根据 token 查找用户
-/// 3. 检查 token 是否过期
-/// 4. 更新用户验证状态
-///
-/// # 数据库更新操作
-/// 验证成功后，通过 verified_token 函数执行以下更新：
-/// - verified = true  -- 标记邮箱已验证
-/// - updated_at = Now()  -- 更新时间戳
-/// - verification_token = NULL  -- 清除验证 token
-/// - token_expires_at = NULL  -- 清除过期时间
+/// 2. 消费邮箱验证令牌，只接受用途为 `verify_email`、未使用且未过期的令牌
+/// 3. 更新用户验证状态
 pub async fn verify_email(
     Query(query_params): Query<VerifyEmailQueryDto>,
     Extension(app_state): Extension<Arc<AppState>>,
@@ -226,46 +848,27 @@ pub async fn verify_email(
         .validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
-    let token = &query_params.token;
-    tracing::info!("开始处理邮箱验证请求，token: {}", token);
-
-    // -- 步骤 2: 根据验证 token 查找用户
-    let result = app_state
+    // -- 步骤 2: 消费邮箱验证令牌
+    let user_id = app_state
         .db_client
-        .get_user(None, None, None, Some(token))
+        .consume_user_token(
+            UserTokenPurpose::VerifyEmail,
+            &hash_user_token(&app_state, &query_params.token),
+        )
         .await
         .map_err(|e| {
-            tracing::error!("查询用户失败: {}", e);
+            tracing::error!("消费邮箱验证令牌失败: {}", e);
             HttpError::server_error(e.to_string())
+        })?
+        .ok_or_else(|| {
+            tracing::warn!("邮箱验证令牌无效、已使用或已过期");
+            HttpError::bad_request("验证链接无效或已过期，请重新发送验证邮件".to_string())
         })?;
 
-    let user = result.ok_or_else(|| {
-        tracing::error!("无效的验证 token: {}", token);
-        HttpError::unauthorized(ErrorMessage::InvalidToken.to_string())
-    })?;
-
-    tracing::info!("找到用户: {}, 邮箱: {}", user.name, user.email);
-
-    // -- 步骤 3: 检查 token 是否过期
-    if let Some(expires_at) = user.token_expires_at {
-        let now = Utc::now();
-        tracing::info!("当前时间: {:?}, Token 过期时间: {:?}", now, expires_at);
-
-        if now > expires_at {
-            tracing::warn!("验证链接已过期，用户: {}, 邮箱: {}", user.name, user.email);
-            return Err(HttpError::bad_request(
-                "验证链接已过期，请重新发送验证邮件".to_string(),
-            ));
-        }
-    } else {
-        tracing::error!("验证 token 不存在，用户: {}, 邮箱: {}", user.name, user.email);
-        return Err(HttpError::bad_request("验证 token 不存在".to_string()));
-    }
-
-    // -- 步骤 4: 更新用户验证状态
-    app_state
+    // -- 步骤 3: 更新用户验证状态
+    let user = app_state
         .db_client
-        .verified_token(token)
+        .verify_user_email(user_id)
         .await
         .map_err(|e| {
             tracing::error!("更新用户验证状态失败: {}", e);
@@ -280,37 +883,17 @@ pub async fn verify_email(
         Err(e) => tracing::error!("发送欢迎邮件失败: {}", e),
     }
 
-    // -- 创建 JWT token
-    let token = token::create_token(
-        &user.id.to_string(),
-        app_state.env.jwt_secret.as_bytes(),
-        app_state.env.jwt_maxage,
-    )
-    .map_err(|e| {
-        tracing::error!("创建 JWT token 失败: {}", e);
-        HttpError::server_error(e.to_string())
-    })?;
-
-    // -- 设置 cookie
-    let cookie_duration = time::Duration::minutes(app_state.env.jwt_maxage * 60);
-    let cookie = Cookie::build(("token", token.clone()))
-        .path("/")
-        .max_age(cookie_duration)
-        .http_only(true)
-        .build();
-
-    let mut headers = HeaderMap::new();
-    headers.append(header::SET_COOKIE, cookie.to_string().parse().unwrap());
-
-    // -- 重定向到前端
-    let redirect = Redirect::to(&app_state.env.frontend_url);
-    let mut response = redirect.into_response();
-    response.headers_mut().extend(headers);
+    // -- 创建 JWT token 与刷新会话，设置 cookie 并重定向到前端
+    let response = login_redirect(&app_state, &user).await?;
 
     tracing::info!("用户 {} 验证完成，重定向到前端", user.email);
     Ok(response)
 }
 
+/// 处理重新发送验证邮件请求
+///
+/// 隐私模式下无论邮箱是否注册、是否已验证都返回相同的响应，邮件在后台发送，
+/// 使响应内容和耗时都不会透露账户状态。
 pub async fn resend_verification_email(
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<ResendVerificationDto>,
@@ -321,30 +904,43 @@ pub async fn resend_verification_email(
 
     tracing::info!("处理重新发送验证邮件请求: {}", body.email);
 
+    let privacy_mode = app_state.env.privacy_mode;
+    let generic_response = Json(Response {
+        status: "success",
+        message: "如果该邮箱已注册且尚未验证，验证邮件将发送到该邮箱，请在 30 分钟内完成验证"
+            .to_string(),
+    });
+
     // -- 查找用户
     let result = app_state
         .db_client
-        .get_user(None, None, Some(&body.email), None)
+        .get_user(None, None, Some(&body.email))
         .await
         .map_err(|e| {
             tracing::error!("查询用户失败: {}", e);
             HttpError::server_error(e.to_string())
         })?;
 
-    let user = result.ok_or_else(|| {
+    let Some(user) = result else {
         tracing::warn!("邮箱地址未注册: {}", body.email);
-        HttpError::bad_request("邮箱地址未注册".to_string())
-    })?;
+        if privacy_mode {
+            return Ok(generic_response);
+        }
+        return Err(HttpError::bad_request("邮箱地址未注册".to_string()));
+    };
 
     // -- 检查是否已经验证过
     if user.verified {
         tracing::warn!("用户邮箱已经验证过了: {}", body.email);
+        if privacy_mode {
+            return Ok(generic_response);
+        }
         return Err(HttpError::bad_request("邮箱已经验证过了".to_string()));
     }
 
-    // -- 生成新的验证 token，有效期设置为 30 分钟
-    let verification_token = uuid::Uuid::new_v4().to_string();
-    let expires_at = Utc::now() + Duration::minutes(30);
+    // -- 生成新的验证 token，之前未使用的验证 token 同时作废
+    let verification_token = token::generate_opaque_token();
+    let expires_at = Utc::now() + UserTokenPurpose::VerifyEmail.ttl();
 
     tracing::info!(
         "为用户 {} 生成新的验证 token，当前时间: {:?}, 过期时间: {:?}",
@@ -353,12 +949,15 @@ pub async fn resend_verification_email(
         expires_at
     );
 
-    let user_id = uuid::Uuid::parse_str(&user.id.to_string()).unwrap();
-
-    // -- 更新验证 token
+    // -- 保存验证 token
     app_state
         .db_client
-        .add_verified_token(user_id, &verification_token, expires_at)
+        .save_user_token(
+            user.id,
+            UserTokenPurpose::VerifyEmail,
+            &hash_user_token(&app_state, &verification_token),
+            expires_at,
+        )
         .await
         .map_err(|e| {
             tracing::error!("更新验证 token 失败: {}", e);
@@ -367,6 +966,17 @@ pub async fn resend_verification_email(
 
     tracing::info!("成功更新用户 {} 的验证 token", user.email);
 
+    // -- 隐私模式下在后台发送邮件，响应耗时与邮箱未注册时一致
+    if privacy_mode {
+        tokio::spawn(async move {
+            match send_verification_email(&user.email, &user.name, &verification_token).await {
+                Ok(_) => tracing::info!("成功重新发送验证邮件给用户: {}", user.email),
+                Err(e) => tracing::error!("重新发送验证邮件失败: {}", e),
+            }
+        });
+        return Ok(generic_response);
+    }
+
     // -- 发送验证邮件
     match send_verification_email(&user.email, &user.name, &verification_token).await {
         Ok(_) => {
@@ -386,6 +996,10 @@ pub async fn resend_verification_email(
     }
 }
 
+/// 处理忘记密码请求 -- 向用户发送密码重置链接
+///
+/// 隐私模式下无论邮箱是否注册都返回相同的响应，邮件在后台发送，
+/// 使响应内容和耗时都不会透露邮箱是否已注册。
 pub async fn forgot_password(
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<ForgotPasswordRequestDto>,
@@ -395,22 +1009,31 @@ pub async fn forgot_password(
 
     tracing::info!("处理忘记密码请求: {}", body.email);
 
+    let privacy_mode = app_state.env.privacy_mode;
+    let generic_response = Json(Response {
+        status: "success",
+        message: "如果该邮箱已注册，密码重置邮件将发送到该邮箱，请在 30 分钟内完成重置".to_string(),
+    });
+
     let result = app_state
         .db_client
-        .get_user(None, None, Some(&body.email), None)
+        .get_user(None, None, Some(&body.email))
         .await
         .map_err(|e| {
             tracing::error!("查询用户失败: {}", e);
             HttpError::server_error(e.to_string())
         })?;
 
-    let user = result.ok_or_else(|| {
+    let Some(user) = result else {
         tracing::warn!("邮箱地址未注册: {}", body.email);
-        HttpError::bad_request("Email not found!".to_string())
-    })?;
+        if privacy_mode {
+            return Ok(generic_response);
+        }
+        return Err(HttpError::bad_request("Email not found!".to_string()));
+    };
 
-    let verification_token = uuid::Uuid::new_v4().to_string();
-    let expires_at = Utc::now() + Duration::minutes(30);
+    let reset_token = token::generate_opaque_token();
+    let expires_at = Utc::now() + UserTokenPurpose::ResetPassword.ttl();
 
     tracing::info!(
         "为用户 {} 生成密码重置 token，过期时间: {:?}",
@@ -418,11 +1041,14 @@ pub async fn forgot_password(
         expires_at
     );
 
-    let user_id = uuid::Uuid::parse_str(&user.id.to_string()).unwrap();
-
     app_state
         .db_client
-        .add_verified_token(user_id, &verification_token, expires_at)
+        .save_user_token(
+            user.id,
+            UserTokenPurpose::ResetPassword,
+            &hash_user_token(&app_state, &reset_token),
+            expires_at,
+        )
         .await
         .map_err(|e| {
             tracing::error!("更新密码重置 token 失败: {}", e);
@@ -431,11 +1057,19 @@ pub async fn forgot_password(
 
     let reset_link = format!(
         "{}/reset-password?token={}",
-        &app_state.env.frontend_url,
-        verification_token
+        &app_state.env.frontend_url, reset_token
     );
 
-    tracing::info!("生成密码重置链接: {}", reset_link);
+    // -- 隐私模式下在后台发送邮件，响应耗时与邮箱未注册时一致
+    if privacy_mode {
+        tokio::spawn(async move {
+            match send_forgot_password_email(&user.email, &reset_link, &user.name).await {
+                Ok(_) => tracing::info!("成功发送密码重置邮件给用户: {}", user.email),
+                Err(e) => tracing::error!("发送密码重置邮件失败: {}", e),
+            }
+        });
+        return Ok(generic_response);
+    }
 
     // -- 发送密码重置邮件
     match send_forgot_password_email(&user.email, &reset_link, &user.name).await {
@@ -463,42 +1097,57 @@ pub async fn reset_password(
     body.validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
-    let result = app_state
+    let token_hash = hash_user_token(&app_state, &body.token);
+
+    // -- 先只查找令牌，新密码不满足密码策略时令牌仍可继续使用
+    let user = match app_state
         .db_client
-        .get_user(None, None, None, Some(&body.token))
+        .find_user_token(UserTokenPurpose::ResetPassword, &token_hash)
         .await
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
-
-    let user = result.ok_or(HttpError::bad_request(
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+    {
+        Some(user_id) => app_state
+            .db_client
+            .get_user(Some(user_id), None, None)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?,
+        None => None,
+    }
+    .ok_or(HttpError::bad_request(
         "Invalid or expired token".to_string(),
     ))?;
 
-    if let Some(expires_at) = user.token_expires_at {
-        if Utc::now() > expires_at {
-            return Err(HttpError::bad_request(
-                "Verification token has expired".to_string(),
-            ))?;
-        }
-    } else {
-        return Err(HttpError::bad_request(
-            "Invalid verification token".to_string(),
-        ))?;
-    }
+    body.validate_with_policy(&app_state.env.password_policy, &[&user.name, &user.email])
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    ensure_password_not_reused(&app_state, &user, &body.new_password).await?;
 
-    let user_id = uuid::Uuid::parse_str(&user.id.to_string()).unwrap();
+    let user_id = app_state
+        .db_client
+        .consume_user_token(UserTokenPurpose::ResetPassword, &token_hash)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request(
+            "Invalid or expired token".to_string(),
+        ))?;
 
-    let hash_password =
-        password::hash(&body.new_password).map_err(|e| HttpError::server_error(e.to_string()))?;
+    let hash_password = password::hash(&body.new_password, &app_state.env.password_hash)
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
 
     app_state
         .db_client
-        .update_user_password(user_id, hash_password)
+        .update_user_password(
+            user_id,
+            hash_password,
+            app_state.env.password_policy.history_size,
+        )
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
+    // -- 密码重置后吊销所有刷新会话，访问令牌已通过令牌版本失效
     app_state
         .db_client
-        .verified_token(&body.token)
+        .revoke_user_sessions(user_id)
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
@@ -509,3 +1158,370 @@ pub async fn reset_password(
 
     Ok(Json(response))
 }
+
+/// 处理登录链接请求 -- 向已验证的邮箱发送一次性登录链接
+///
+/// 无论邮箱是否注册都返回相同的响应，避免泄露账户是否存在。
+/// 未完成邮箱验证的账户不会收到登录链接。
+pub async fn request_magic_link(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<MagicLinkRequestDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let response = Json(Response {
+        status: "success",
+        message: format!(
+            "如果该邮箱已注册，登录链接将发送到该邮箱，{} 分钟内有效",
+            UserTokenPurpose::MagicLogin.ttl().num_minutes()
+        ),
+    });
+
+    let result = app_state
+        .db_client
+        .get_user(None, None, Some(&body.email))
+        .await
+        .map_err(|e| {
+            tracing::error!("查询用户失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    let Some(user) = result.filter(|user| user.verified) else {
+        tracing::warn!("登录链接请求的邮箱未注册或未验证: {}", body.email);
+        return Ok(response);
+    };
+
+    let magic_token = token::generate_opaque_token();
+    let expires_at = Utc::now() + UserTokenPurpose::MagicLogin.ttl();
+
+    app_state
+        .db_client
+        .save_user_token(
+            user.id,
+            UserTokenPurpose::MagicLogin,
+            &hash_user_token(&app_state, &magic_token),
+            expires_at,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("保存登录链接令牌失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    if let Err(e) = send_magic_link_email(&user.email, &user.name, &magic_token).await {
+        tracing::error!("发送登录链接邮件失败: {}", e);
+        return Err(HttpError::server_error("发送邮件失败".to_string()));
+    }
+
+    tracing::info!("成功发送登录链接给用户: {}", user.email);
+    Ok(response)
+}
+
+/// 处理登录链接 -- 消费一次性令牌，写入认证 cookie 并重定向到前端
+///
+/// 链接无效、已使用或已过期时重定向到前端登录页 `/login?error=magic_link_invalid`；
+/// 开启两步验证的用户会被重定向到前端两步验证页。
+pub async fn verify_magic_link(
+    Query(query_params): Query<MagicLinkQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    query_params
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user_id = app_state
+        .db_client
+        .consume_user_token(
+            UserTokenPurpose::MagicLogin,
+            &hash_user_token(&app_state, &query_params.token),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("消费登录链接令牌失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    let Some(user_id) = user_id else {
+        tracing::warn!("登录链接无效、已使用或已过期");
+        return Ok(login_error_redirect(&app_state, "magic_link_invalid"));
+    };
+
+    let user = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+
+    tracing::info!("用户 {} 通过登录链接登录", user.email);
+    first_factor_redirect(&app_state, &user).await
+}
+
+/// 处理解锁链接 -- 消费一次性解锁令牌，解除账户的登录锁定并重定向到前端登录页
+///
+/// 链接无效、已使用或已过期时重定向到前端登录页 `/login?error=unlock_link_invalid`。
+pub async fn unlock_account(
+    Query(query_params): Query<UnlockAccountQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    query_params
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user_id = app_state
+        .db_client
+        .consume_user_token(
+            UserTokenPurpose::UnlockAccount,
+            &hash_user_token(&app_state, &query_params.token),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("消费解锁令牌失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    let Some(user_id) = user_id else {
+        tracing::warn!("解锁链接无效、已使用或已过期");
+        return Ok(login_error_redirect(&app_state, "unlock_link_invalid"));
+    };
+
+    let user = app_state
+        .db_client
+        .unlock_user(user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    tracing::info!("用户 {} 通过邮件链接解除了登录锁定", user.email);
+
+    let url = format!(
+        "{}/login?unlocked=true",
+        app_state.env.frontend_url.trim_end_matches('/')
+    );
+    Ok(Redirect::to(&url).into_response())
+}
+
+/// 处理刷新令牌请求 -- 轮换刷新令牌并签发新的访问令牌
+///
+/// # 轮换流程
+/// 1. 从 `refresh_token` cookie 或请求体中读取刷新令牌
+/// 2. 根据令牌哈希查找会话
+/// 3. 若令牌已被轮换或吊销，视为重放攻击，吊销整个会话族
+/// 4. 检查令牌是否过期
+/// 5. 将旧令牌标记为已轮换，在同一会话族下保存新令牌
+pub async fn refresh(
+    Extension(app_state): Extension<Arc<AppState>>,
+    cookie_jar: CookieJar,
+    body: Bytes,
+) -> Result<impl IntoResponse, HttpError> {
+    // -- 步骤 1: 优先读取 cookie，其次读取请求体（请求体可以为空）
+    let refresh_token = cookie_jar
+        .get(REFRESH_TOKEN_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        .or_else(|| {
+            serde_json::from_slice::<RefreshTokenDto>(&body)
+                .ok()
+                .and_then(|body| body.refresh_token)
+        })
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::TokenNotProvided.to_string()))?;
+
+    // -- 步骤 2: 根据令牌哈希查找会话
+    let session = app_state
+        .db_client
+        .get_session_by_token_hash(&token::hash_opaque_token(&refresh_token))
+        .await
+        .map_err(|e| {
+            tracing::error!("查询会话失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
+
+    // -- 步骤 3: 重放检测，旧令牌再次出现说明令牌可能已泄露
+    if session.rotated_at.is_some() || session.revoked_at.is_some() {
+        tracing::warn!(
+            "检测到刷新令牌重放，吊销会话族: {}, 用户ID: {}",
+            session.family_id,
+            session.user_id
+        );
+
+        app_state
+            .db_client
+            .revoke_session_family(session.family_id)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        return Err(HttpError::unauthorized(
+            ErrorMessage::InvalidToken.to_string(),
+        ));
+    }
+
+    // -- 步骤 4: 检查令牌是否过期
+    if Utc::now() > session.expires_at {
+        return Err(HttpError::unauthorized(
+            ErrorMessage::InvalidToken.to_string(),
+        ));
+    }
+
+    // -- 步骤 5: 轮换令牌
+    let new_refresh_token = token::generate_opaque_token();
+    let expires_at = Utc::now() + Duration::minutes(app_state.env.refresh_token_maxage);
+
+    let rotated = app_state
+        .db_client
+        .rotate_session(
+            session.id,
+            &token::hash_opaque_token(&new_refresh_token),
+            expires_at,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("轮换会话失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    // -- 并发请求抢先轮换了同一令牌，同样按重放处理
+    if rotated.is_none() {
+        tracing::warn!("刷新令牌被并发使用，吊销会话族: {}", session.family_id);
+
+        app_state
+            .db_client
+            .revoke_session_family(session.family_id)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        return Err(HttpError::unauthorized(
+            ErrorMessage::InvalidToken.to_string(),
+        ));
+    }
+
+    let user = app_state
+        .db_client
+        .get_user(Some(session.user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+
+    ensure_active(&user)?;
+
+    // -- 密码已过期时不再续签访问令牌，必须先修改密码
+    if user.password_change_required(app_state.env.password_policy.max_age_days) {
+        return password_change_required_response(&app_state, &user);
+    }
+
+    let token = create_access_token(&app_state, &user, session.family_id)?;
+    let headers = auth_cookie_headers(&app_state, &token, &new_refresh_token);
+
+    let mut response = Json(UserLoginResponseDto {
+        status: "success".to_string(),
+        token,
+        refresh_token: new_refresh_token,
+    })
+    .into_response();
+    response.headers_mut().extend(headers);
+
+    Ok(response)
+}
+
+/// 处理退出登录请求 -- 吊销当前访问令牌与刷新会话，并清除 cookie
+///
+/// 该接口不要求令牌有效：令牌已过期或无法解析时仍会清除 cookie。
+pub async fn logout(
+    Extension(app_state): Extension<Arc<AppState>>,
+    cookie_jar: CookieJar,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, HttpError> {
+    // -- 将访问令牌加入黑名单，直到其自然过期
+    let claims = extract_access_token(&cookie_jar, &headers)
+        .and_then(|token| token::decode_token(token, &app_state.jwt_keys).ok());
+
+    if let Some(claims) = claims {
+        let jti = Uuid::parse_str(&claims.jti);
+        let user_id = Uuid::parse_str(&claims.sub);
+
+        if let (Ok(jti), Ok(user_id)) = (jti, user_id) {
+            let expires_at =
+                DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+
+            app_state
+                .db_client
+                .revoke_token(jti, user_id, expires_at)
+                .await
+                .map_err(|e| {
+                    tracing::error!("吊销访问令牌失败: {}", e);
+                    HttpError::server_error(e.to_string())
+                })?;
+        }
+
+        // -- 只使用 Bearer 令牌、没有刷新令牌 cookie 的客户端按令牌中的会话ID吊销会话族
+        if let Some(session_id) = claims.sid.and_then(|sid| Uuid::parse_str(&sid).ok()) {
+            app_state
+                .db_client
+                .revoke_session_family(session_id)
+                .await
+                .map_err(|e| HttpError::server_error(e.to_string()))?;
+        }
+    }
+
+    // -- 吊销刷新令牌所在的会话族
+    if let Some(refresh_token) = cookie_jar.get(REFRESH_TOKEN_COOKIE) {
+        let session = app_state
+            .db_client
+            .get_session_by_token_hash(&token::hash_opaque_token(refresh_token.value()))
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        if let Some(session) = session {
+            app_state
+                .db_client
+                .revoke_session_family(session.family_id)
+                .await
+                .map_err(|e| HttpError::server_error(e.to_string()))?;
+        }
+    }
+
+    let mut response = Json(Response {
+        status: "success",
+        message: "Logged out successfully".to_string(),
+    })
+    .into_response();
+    response.headers_mut().extend(clear_auth_cookie_headers());
+
+    Ok(response)
+}
+
+/// 处理退出所有设备请求 -- 递增令牌版本并吊销该用户的全部会话
+pub async fn logout_all(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<impl IntoResponse, HttpError> {
+    let user_id = auth.id;
+
+    app_state
+        .db_client
+        .increment_token_version(user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("递增令牌版本失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    app_state
+        .db_client
+        .revoke_user_sessions(user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("吊销用户会话失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    tracing::info!("用户 {} 已退出所有设备", auth.id);
+
+    let mut response = Json(Response {
+        status: "success",
+        message: "Logged out from all devices".to_string(),
+    })
+    .into_response();
+    response.headers_mut().extend(clear_auth_cookie_headers());
+
+    Ok(response)
+}
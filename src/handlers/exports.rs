@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Query,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_DISPOSITION},
+        StatusCode,
+    },
+    response::IntoResponse,
+    routing::get,
+    Extension, Json, Router,
+};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    db::{
+        ApiKeyExt, AuditEventExt, DataExportExt, IdentityExt, LoginAttemptExt, SessionExt,
+        WebauthnExt,
+    },
+    dtos::{
+        DataExportQueryDto, FilterApiKeyDto, FilterPasskeyDto, FilterSessionDto, FilterUserDto,
+        PersonalDataExportDto, Response,
+    },
+    error::{ErrorMessage, HttpError},
+    handlers::{admin::record_audit_event, auth::hash_user_token},
+    mail::mails::send_data_export_email,
+    middleware::AuthUser,
+    models::{AuditAction, User},
+    utils::token,
+    AppState,
+};
+
+/// 导出文件的下载路由 -- 挂载在 `/api/exports` 下，凭邮件中的下载令牌访问，不需要登录
+pub fn exports_handler() -> Router {
+    Router::new().route("/download", get(download_data_export))
+}
+
+/// 下载链接的有效期（小时），过期后导出文件由后台任务删除
+const DATA_EXPORT_EXPIRY_HOURS: i64 = 48;
+
+/// 申请导出个人数据 -- 在后台生成导出文件，完成后通过邮件发送下载链接
+pub async fn request_data_export(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<impl IntoResponse, HttpError> {
+    let user = auth.user(&app_state).await?;
+
+    let download_token = token::generate_opaque_token();
+    let expires_at = Utc::now() + Duration::hours(DATA_EXPORT_EXPIRY_HOURS);
+
+    let data_export = app_state
+        .db_client
+        .save_data_export(
+            user.id,
+            &hash_user_token(&app_state, &download_token),
+            expires_at,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("创建个人数据导出失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    record_audit_event(
+        &app_state,
+        &auth,
+        user.id,
+        AuditAction::DataExportRequested,
+        json!({}),
+    )
+    .await?;
+
+    tracing::info!("用户 {} 申请导出个人数据", user.email);
+
+    tokio::spawn(generate_data_export(
+        app_state.clone(),
+        user,
+        data_export.id,
+        download_token,
+    ));
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(Response {
+            status: "success",
+            message: "Your data export is being prepared, a download link will be emailed to you"
+                .to_string(),
+        }),
+    ))
+}
+
+/// 下载导出文件 -- 链接在有效期内可以重复使用
+pub async fn download_data_export(
+    Query(query_params): Query<DataExportQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    query_params
+        .validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let data_export = app_state
+        .db_client
+        .get_data_export_by_token_hash(&hash_user_token(&app_state, &query_params.token))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| {
+            HttpError::new(
+                ErrorMessage::DataExportNotFound.to_string(),
+                StatusCode::NOT_FOUND,
+            )
+        })?;
+
+    tracing::info!("用户 {} 下载了个人数据导出", data_export.user_id);
+
+    let filename = format!(
+        "personal-data-{}.json",
+        data_export
+            .completed_at
+            .unwrap_or_else(Utc::now)
+            .format("%Y%m%d")
+    );
+
+    Ok((
+        [
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+            (CACHE_CONTROL, "no-store".to_string()),
+        ],
+        Json(data_export.data.unwrap_or_default()),
+    ))
+}
+
+/// 在后台生成导出文件并发送下载链接 -- 生成失败时删除导出任务
+async fn generate_data_export(
+    app_state: Arc<AppState>,
+    user: User,
+    export_id: Uuid,
+    download_token: String,
+) {
+    let result = match collect_personal_data(&app_state, &user).await {
+        Ok(data) => {
+            app_state
+                .db_client
+                .complete_data_export(export_id, data)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = result {
+        tracing::error!("生成个人数据导出失败，用户ID: {}: {}", user.id, e);
+
+        if let Err(e) = app_state.db_client.delete_data_export(export_id).await {
+            tracing::error!("删除个人数据导出失败: {}", e);
+        }
+        return;
+    }
+
+    match send_data_export_email(
+        &user.email,
+        &user.name,
+        &download_token,
+        DATA_EXPORT_EXPIRY_HOURS,
+    )
+    .await
+    {
+        Ok(_) => tracing::info!("成功发送个人数据导出邮件给用户: {}", user.email),
+        Err(e) => tracing::error!("发送个人数据导出邮件失败: {}", e),
+    }
+}
+
+/// 汇总服务保存的该用户的全部数据
+async fn collect_personal_data(
+    app_state: &AppState,
+    user: &User,
+) -> Result<serde_json::Value, sqlx::Error> {
+    let db_client = &app_state.db_client;
+
+    let sessions = db_client.get_user_sessions(user.id).await?;
+    let login_history = db_client.get_login_attempts(user.id).await?;
+    let audit_events = db_client.get_user_audit_events(user.id).await?;
+    let identities = db_client.get_user_identities(user.id).await?;
+    let passkeys = db_client.get_webauthn_credentials(user.id).await?;
+    let api_keys = db_client.get_api_keys(user.id).await?;
+
+    let export = PersonalDataExportDto {
+        exported_at: Utc::now(),
+        profile: FilterUserDto::filter_user(user),
+        mfa_enabled: user.mfa_enabled,
+        password_changed_at: user.password_changed_at,
+        sessions: FilterSessionDto::filter_sessions(&sessions),
+        login_history,
+        audit_events,
+        identities,
+        passkeys: FilterPasskeyDto::filter_passkeys(&passkeys),
+        api_keys: FilterApiKeyDto::filter_api_keys(&api_keys),
+    };
+
+    // -- 导出结构只包含可序列化的字段，不会失败
+    Ok(json!(export))
+}
@@ -0,0 +1,344 @@
+use std::sync::Arc;
+
+use axum::{response::IntoResponse, routing::post, Extension, Json, Router};
+use validator::Validate;
+
+use crate::{
+    db::{RecoveryCodeExt, UserExt},
+    dtos::{RecoveryCodesResponseDto, Response, TotpCodeDto, TotpReauthDto, TotpSetupResponseDto},
+    error::{ErrorMessage, HttpError},
+    middleware::AuthUser,
+    models::User,
+    utils::{password, token, totp},
+    AppState,
+};
+
+pub fn mfa_handler() -> Router {
+    Router::new()
+        .route("/totp/setup", post(setup_totp))
+        .route("/totp/confirm", post(confirm_totp))
+        .route("/totp/disable", post(disable_totp))
+        .route("/totp/reenroll", post(reenroll_totp))
+        .route("/recovery-codes", post(regenerate_recovery_codes))
+}
+
+/// 每次生成的恢复码数量
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// 校验用户当前 TOTP 密钥生成的验证码，并记录已使用的时间步防止重放
+///
+/// # 错误类型
+/// - `MfaNotEnabled` -- 用户未启用两步验证
+/// - `InvalidMfaCode` -- 验证码错误或已被使用
+pub async fn check_totp_code(
+    app_state: &AppState,
+    user: &User,
+    code: &str,
+) -> Result<(), HttpError> {
+    let secret = user
+        .totp_secret
+        .as_deref()
+        .filter(|_| user.mfa_enabled)
+        .ok_or_else(|| HttpError::bad_request(ErrorMessage::MfaNotEnabled.to_string()))?;
+
+    let step = totp::verify(secret, code, user.totp_last_used_step)
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::InvalidMfaCode.to_string()))?;
+
+    // -- 条件更新保证并发请求中同一验证码只有一次能通过
+    let accepted = app_state
+        .db_client
+        .update_totp_last_used_step(user.id, step)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !accepted {
+        tracing::warn!("TOTP 验证码被重复使用，用户ID: {}", user.id);
+        return Err(HttpError::unauthorized(
+            ErrorMessage::InvalidMfaCode.to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// 生成新的一组恢复码 -- 数据库中只保存 argon2 哈希，明文只返回这一次
+async fn issue_recovery_codes(app_state: &AppState, user: &User) -> Result<Vec<String>, HttpError> {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| token::generate_recovery_code())
+        .collect();
+
+    let code_hashes = codes
+        .iter()
+        .map(|code| {
+            password::hash(
+                token::normalize_recovery_code(code),
+                &app_state.env.password_hash,
+            )
+        })
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    app_state
+        .db_client
+        .replace_recovery_codes(user.id, &code_hashes)
+        .await
+        .map_err(|e| {
+            tracing::error!("保存恢复码失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    Ok(codes)
+}
+
+/// 校验并消费恢复码 -- 作为 TOTP 验证码之外的第二因素
+///
+/// # 错误类型
+/// - `MfaNotEnabled` -- 用户未启用两步验证
+/// - `InvalidRecoveryCode` -- 恢复码错误或已被使用
+pub async fn check_recovery_code(
+    app_state: &AppState,
+    user: &User,
+    code: &str,
+) -> Result<(), HttpError> {
+    if !user.mfa_enabled {
+        return Err(HttpError::bad_request(
+            ErrorMessage::MfaNotEnabled.to_string(),
+        ));
+    }
+
+    let normalized = token::normalize_recovery_code(code);
+    if normalized.is_empty() {
+        return Err(HttpError::unauthorized(
+            ErrorMessage::InvalidRecoveryCode.to_string(),
+        ));
+    }
+
+    let codes = app_state
+        .db_client
+        .get_unused_recovery_codes(user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    // -- 哈希带有随机盐，只能逐个比对
+    let matched = codes.iter().find(|recovery_code| {
+        password::compare(
+            &normalized,
+            &recovery_code.code_hash,
+            &app_state.env.password_hash,
+        )
+        .unwrap_or(false)
+    });
+
+    let matched = matched
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::InvalidRecoveryCode.to_string()))?;
+
+    let consumed = app_state
+        .db_client
+        .use_recovery_code(matched.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !consumed {
+        return Err(HttpError::unauthorized(
+            ErrorMessage::InvalidRecoveryCode.to_string(),
+        ));
+    }
+
+    tracing::info!(
+        "用户 {} 使用恢复码完成两步验证，剩余 {} 个",
+        user.email,
+        codes.len() - 1
+    );
+
+    Ok(())
+}
+
+/// 敏感操作前的二次确认 -- 同时校验登录密码和 TOTP 验证码
+async fn reauthenticate(
+    app_state: &AppState,
+    user: &User,
+    body: &TotpReauthDto,
+) -> Result<(), HttpError> {
+    let password_matched =
+        password::compare(&body.password, &user.password, &app_state.env.password_hash)
+            .map_err(|_| HttpError::bad_request(ErrorMessage::WrongCredentials.to_string()))?;
+
+    if !password_matched {
+        return Err(HttpError::bad_request(
+            ErrorMessage::WrongCredentials.to_string(),
+        ));
+    }
+
+    check_totp_code(app_state, user, &body.code).await
+}
+
+/// 生成并保存待确认的 TOTP 密钥，返回密钥与 otpauth URI
+async fn start_totp_enrollment(
+    app_state: &AppState,
+    user: &User,
+) -> Result<TotpSetupResponseDto, HttpError> {
+    let secret = totp::generate_secret();
+    let otpauth_url = totp::otpauth_url(&secret, &app_state.env.totp_issuer, &user.email)
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    app_state
+        .db_client
+        .set_totp_pending_secret(user.id, &secret)
+        .await
+        .map_err(|e| {
+            tracing::error!("保存 TOTP 密钥失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    Ok(TotpSetupResponseDto {
+        status: "success".to_string(),
+        secret,
+        otpauth_url,
+    })
+}
+
+/// 开始绑定 TOTP -- 生成新密钥，需调用确认接口后才会生效
+pub async fn setup_totp(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<impl IntoResponse, HttpError> {
+    let user = &auth.user(&app_state).await?;
+
+    if user.mfa_enabled {
+        return Err(HttpError::bad_request(
+            ErrorMessage::MfaAlreadyEnabled.to_string(),
+        ));
+    }
+
+    let response = start_totp_enrollment(&app_state, user).await?;
+
+    tracing::info!("用户 {} 开始绑定 TOTP", user.email);
+    Ok(Json(response))
+}
+
+/// 确认绑定 TOTP -- 使用待确认密钥生成的验证码完成绑定并开启两步验证
+///
+/// 首次开启两步验证时会同时生成一组恢复码；重新绑定时保留原有恢复码。
+pub async fn confirm_totp(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<TotpCodeDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = &auth.user(&app_state).await?;
+
+    let pending_secret = user
+        .totp_pending_secret
+        .as_deref()
+        .ok_or_else(|| HttpError::bad_request(ErrorMessage::MfaSetupNotStarted.to_string()))?;
+
+    let step = totp::verify(pending_secret, &body.code, None)
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::bad_request(ErrorMessage::InvalidMfaCode.to_string()))?;
+
+    app_state
+        .db_client
+        .enable_totp(user.id, step)
+        .await
+        .map_err(|e| {
+            tracing::error!("启用 TOTP 失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    tracing::info!("用户 {} 已启用两步验证", user.email);
+
+    let recovery_codes = if user.mfa_enabled {
+        Vec::new()
+    } else {
+        issue_recovery_codes(&app_state, user).await?
+    };
+
+    Ok(Json(RecoveryCodesResponseDto {
+        status: "success".to_string(),
+        recovery_codes,
+    }))
+}
+
+/// 关闭两步验证 -- 需要同时提供登录密码和当前验证码
+pub async fn disable_totp(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<TotpReauthDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = &auth.user(&app_state).await?;
+
+    reauthenticate(&app_state, user, &body).await?;
+
+    app_state
+        .db_client
+        .disable_totp(user.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("关闭 TOTP 失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    app_state
+        .db_client
+        .delete_recovery_codes(user.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("删除恢复码失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    tracing::info!("用户 {} 已关闭两步验证", user.email);
+
+    Ok(Json(Response {
+        status: "success",
+        message: "Two-factor authentication disabled".to_string(),
+    }))
+}
+
+/// 重新绑定 TOTP -- 验证当前密钥后生成新密钥，确认前旧密钥仍然有效
+pub async fn reenroll_totp(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<TotpReauthDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = &auth.user(&app_state).await?;
+
+    reauthenticate(&app_state, user, &body).await?;
+
+    let response = start_totp_enrollment(&app_state, user).await?;
+
+    tracing::info!("用户 {} 开始重新绑定 TOTP", user.email);
+    Ok(Json(response))
+}
+
+/// 重新生成恢复码 -- 需要同时提供登录密码和当前验证码，旧恢复码全部作废
+pub async fn regenerate_recovery_codes(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<TotpReauthDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = &auth.user(&app_state).await?;
+
+    reauthenticate(&app_state, user, &body).await?;
+
+    let recovery_codes = issue_recovery_codes(&app_state, user).await?;
+
+    tracing::info!("用户 {} 重新生成了恢复码", user.email);
+
+    Ok(Json(RecoveryCodesResponseDto {
+        status: "success".to_string(),
+        recovery_codes,
+    }))
+}
@@ -0,0 +1,326 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query},
+    http::header,
+    response::{IntoResponse, Redirect, Response as AxumResponse},
+    routing::get,
+    Extension, Json, Router,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use chrono::{Duration, Utc};
+
+use crate::{
+    db::{IdentityExt, UserExt},
+    dtos::{OAuthCallbackQueryDto, OAuthProvidersResponseDto},
+    error::HttpError,
+    handlers::auth::{first_factor_redirect, login_error_redirect},
+    models::User,
+    oauth::ExternalIdentity,
+    utils::{password, token},
+    AppState,
+};
+
+/// 第三方登录路由 -- 挂载在 `/api/auth/oauth` 下
+pub fn oauth_handler() -> Router {
+    Router::new()
+        .route("/providers", get(get_providers))
+        .route("/{provider}/authorize", get(authorize))
+        .route("/{provider}/callback", get(callback))
+}
+
+/// 授权状态有效期（分钟）
+const OAUTH_STATE_MAXAGE: i64 = 10;
+
+/// 保存 state 的 cookie -- 将授权流程绑定到发起它的浏览器
+const OAUTH_STATE_COOKIE: &str = "oauth_state";
+
+/// 获取已配置的第三方登录提供方
+pub async fn get_providers(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    Ok(Json(OAuthProvidersResponseDto {
+        status: "success".to_string(),
+        providers: app_state.identity_providers.names(),
+    }))
+}
+
+/// 发起第三方登录 -- 生成 state、nonce 与 PKCE 校验码后重定向到提供方授权页
+///
+/// state 同时写入 cookie，回调时必须与之一致，防止攻击者将自己的回调链接发给受害者完成登录
+pub async fn authorize(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(provider_name): Path<String>,
+) -> Result<impl IntoResponse, HttpError> {
+    let provider = app_state
+        .identity_providers
+        .get(&provider_name)
+        .ok_or_else(|| HttpError::bad_request("Unknown identity provider"))?;
+
+    let state = token::generate_opaque_token();
+    let nonce = token::generate_opaque_token();
+    let pkce_verifier = token::generate_opaque_token();
+
+    let url = provider
+        .authorization_url(&state, &nonce, &pkce_verifier)
+        .await
+        .map_err(|e| {
+            tracing::error!("构建 {} 授权地址失败: {}", provider_name, e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    let expires_at = Utc::now() + Duration::minutes(OAUTH_STATE_MAXAGE);
+    app_state
+        .db_client
+        .save_oauth_state(
+            &token::hash_opaque_token(&state),
+            &provider_name,
+            &pkce_verifier,
+            &nonce,
+            expires_at,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("保存授权状态失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    let cookie = state_cookie(state, time::Duration::minutes(OAUTH_STATE_MAXAGE));
+
+    Ok((
+        [(header::SET_COOKIE, cookie.to_string())],
+        Redirect::to(&url),
+    ))
+}
+
+/// 第三方登录回调 -- 校验 state 并换取身份，成功后写入认证 cookie 并重定向到前端
+///
+/// 失败时重定向到前端登录页 `/login?error=...`，错误码如下：
+/// - `oauth_denied` -- 用户在提供方拒绝授权
+/// - `oauth_failed` -- state 无效或过期、授权码换取失败
+/// - `email_not_verified` -- 提供方未返回已验证的邮箱
+/// - `account_not_verified` -- 同邮箱的本地账户尚未完成邮箱验证，不能自动关联
+pub async fn callback(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(provider_name): Path<String>,
+    Query(query): Query<OAuthCallbackQueryDto>,
+    cookie_jar: CookieJar,
+) -> AxumResponse {
+    let bound_state = cookie_jar
+        .get(OAUTH_STATE_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+
+    let mut response = if let Some(error) = &query.error {
+        tracing::warn!("{} 授权失败: {}", provider_name, error);
+        login_error_redirect(&app_state, "oauth_denied")
+    } else {
+        match complete_login(&app_state, &provider_name, &query, bound_state.as_deref()).await {
+            Ok(response) => response,
+            Err(error) => login_error_redirect(&app_state, error),
+        }
+    };
+
+    // -- state 只能使用一次，无论登录是否成功都清除 cookie
+    let cookie = state_cookie(String::new(), time::Duration::ZERO);
+    response
+        .headers_mut()
+        .append(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+
+    response
+}
+
+/// 完成第三方登录，失败时返回前端错误码
+///
+/// `bound_state` 为发起授权时写入 cookie 的 state
+async fn complete_login(
+    app_state: &AppState,
+    provider_name: &str,
+    query: &OAuthCallbackQueryDto,
+    bound_state: Option<&str>,
+) -> Result<AxumResponse, &'static str> {
+    let (Some(code), Some(state)) = (&query.code, &query.state) else {
+        return Err("oauth_failed");
+    };
+
+    // -- 先校验 cookie 再读取授权状态，不匹配的回调不会消耗受害者自己发起的授权
+    if !state_matches(bound_state, state) {
+        tracing::warn!("{} 回调的 state 与发起授权的浏览器不匹配", provider_name);
+        return Err("oauth_failed");
+    }
+
+    let provider = app_state
+        .identity_providers
+        .get(provider_name)
+        .ok_or("oauth_failed")?;
+
+    let oauth_state = app_state
+        .db_client
+        .take_oauth_state(&token::hash_opaque_token(state))
+        .await
+        .map_err(|e| {
+            tracing::error!("读取授权状态失败: {}", e);
+            "oauth_failed"
+        })?
+        .filter(|oauth_state| oauth_state.provider == provider_name)
+        .filter(|oauth_state| oauth_state.expires_at > Utc::now())
+        .ok_or_else(|| {
+            tracing::warn!("{} 回调的 state 无效或已过期", provider_name);
+            "oauth_failed"
+        })?;
+
+    let identity = provider
+        .exchange_code(code, &oauth_state.nonce, &oauth_state.pkce_verifier)
+        .await
+        .map_err(|e| {
+            tracing::warn!("{} 授权码换取失败: {}", provider_name, e);
+            "oauth_failed"
+        })?;
+
+    let user = find_or_create_user(app_state, provider_name, &identity).await?;
+
+    tracing::info!("用户 {} 通过 {} 登录", user.email, provider_name);
+
+    // -- 第三方登录只替代密码，开启两步验证的用户仍需完成第二因素
+    first_factor_redirect(app_state, &user)
+        .await
+        .map_err(|_| "oauth_failed")
+}
+
+/// 回调中的 state 是否与 cookie 中保存的一致
+fn state_matches(bound_state: Option<&str>, state: &str) -> bool {
+    bound_state.is_some_and(|bound_state| !bound_state.is_empty() && bound_state == state)
+}
+
+/// 构建保存 state 的 cookie -- 只发送给第三方登录接口，`SameSite=Lax` 允许提供方重定向回来时携带
+fn state_cookie(state: String, max_age: time::Duration) -> Cookie<'static> {
+    Cookie::build((OAUTH_STATE_COOKIE, state))
+        .path("/api/auth/oauth")
+        .max_age(max_age)
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+/// 查找第三方身份对应的本地用户
+///
+/// 依次尝试：已关联的身份 -> 按已验证邮箱关联已有账户 -> 创建新账户
+async fn find_or_create_user(
+    app_state: &AppState,
+    provider_name: &str,
+    identity: &ExternalIdentity,
+) -> Result<User, &'static str> {
+    let server_error = |e: sqlx::Error| {
+        tracing::error!("第三方登录数据库操作失败: {}", e);
+        "oauth_failed"
+    };
+
+    if let Some(linked) = app_state
+        .db_client
+        .get_identity(provider_name, &identity.subject)
+        .await
+        .map_err(server_error)?
+    {
+        app_state
+            .db_client
+            .touch_identity(linked.id)
+            .await
+            .map_err(server_error)?;
+
+        return app_state
+            .db_client
+            .get_user(Some(linked.user_id), None, None)
+            .await
+            .map_err(server_error)?
+            .ok_or("oauth_failed");
+    }
+
+    // -- 只有提供方验证过的邮箱才能用于关联或创建账户
+    let email = identity
+        .email
+        .as_deref()
+        .filter(|_| identity.email_verified)
+        .ok_or("email_not_verified")?;
+
+    let existing = app_state
+        .db_client
+        .get_user(None, None, Some(email))
+        .await
+        .map_err(server_error)?;
+
+    if let Some(user) = existing {
+        // -- 未验证的本地账户可能是他人抢注的，自动关联会让抢注者保留密码登录的能力
+        if !user.verified {
+            return Err("account_not_verified");
+        }
+
+        app_state
+            .db_client
+            .link_identity(user.id, provider_name, &identity.subject, Some(email))
+            .await
+            .map_err(server_error)?;
+
+        tracing::info!("用户 {} 关联了 {} 身份", user.email, provider_name);
+        return Ok(user);
+    }
+
+    let name = identity
+        .name
+        .clone()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| email.split('@').next().unwrap_or(email).to_string());
+    let name: String = name.chars().take(100).collect();
+
+    // -- 新账户使用随机密码，用户可通过忘记密码流程设置本地密码
+    let password_hash =
+        password::hash(token::generate_opaque_token(), &app_state.env.password_hash)
+            .map_err(|_| "oauth_failed")?;
+
+    let user = app_state
+        .db_client
+        .save_identity_user(
+            &name,
+            email,
+            &password_hash,
+            provider_name,
+            &identity.subject,
+        )
+        .await
+        .map_err(server_error)?;
+
+    tracing::info!("通过 {} 创建了新用户 {}", provider_name, user.email);
+    Ok(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_must_match_cookie() {
+        assert!(state_matches(Some("state-value"), "state-value"));
+
+        // -- 没有 cookie 说明授权不是由该浏览器发起的，例如攻击者发来的回调链接
+        assert!(!state_matches(None, "state-value"));
+        assert!(!state_matches(Some("other-state"), "state-value"));
+        assert!(!state_matches(Some(""), ""));
+    }
+
+    #[test]
+    fn state_cookie_attributes() {
+        let cookie = state_cookie(
+            "state-value".to_string(),
+            time::Duration::minutes(OAUTH_STATE_MAXAGE),
+        );
+
+        assert_eq!(cookie.value(), "state-value");
+        assert_eq!(cookie.path(), Some("/api/auth/oauth"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::minutes(OAUTH_STATE_MAXAGE))
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post, put},
+    Extension, Json, Router,
+};
+use validator::Validate;
+
+use crate::{
+    db::RoleExt,
+    dtos::{
+        CreateRoleDto, PermissionListResponseDto, Response, RoleListResponseDto, RoleResponseDto,
+        UpdateRoleDto,
+    },
+    error::{ErrorMessage, HttpError},
+    middleware::{guard::RequirePermission, AuthUser},
+    models::{Permission, ADMIN_ROLE},
+    AppState,
+};
+
+/// 角色管理路由 -- 挂载在 `/api/roles` 下，查看需要 `roles:read`，修改需要 `roles:write`
+pub fn roles_handler() -> Router {
+    let read = || RequirePermission::new(Permission::ReadRoles);
+    let write = || RequirePermission::new(Permission::ManageRoles);
+
+    Router::new()
+        .route(
+            "/",
+            get(get_roles)
+                .layer(read())
+                .merge(post(create_role).layer(write())),
+        )
+        .route("/permissions", get(get_permissions).layer(read()))
+        .route(
+            "/{name}",
+            get(get_role)
+                .layer(read())
+                .merge(put(update_role).delete(delete_role).layer(write())),
+        )
+}
+
+/// 获取全部角色及其权限
+pub async fn get_roles(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let roles = app_state.db_client.get_roles().await.map_err(|e| {
+        tracing::error!("获取角色列表失败: {}", e);
+        HttpError::server_error(e.to_string())
+    })?;
+
+    Ok(Json(RoleListResponseDto {
+        status: "success".to_string(),
+        roles,
+    }))
+}
+
+/// 获取全部可分配的权限
+pub async fn get_permissions(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let permissions = app_state.db_client.get_permissions().await.map_err(|e| {
+        tracing::error!("获取权限列表失败: {}", e);
+        HttpError::server_error(e.to_string())
+    })?;
+
+    Ok(Json(PermissionListResponseDto {
+        status: "success".to_string(),
+        permissions,
+    }))
+}
+
+pub async fn get_role(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, HttpError> {
+    let role = app_state
+        .db_client
+        .get_role(&name)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(role_not_found)?;
+
+    Ok(Json(RoleResponseDto {
+        status: "success".to_string(),
+        role,
+    }))
+}
+
+/// 创建自定义角色 -- 权限必须是 `permissions` 表中已有的权限，且不能超出调用者自身的权限
+pub async fn create_role(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<CreateRoleDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    ensure_grantable(&auth, &body.permissions)?;
+
+    let role = app_state
+        .db_client
+        .create_role(&body.name, body.description.trim(), &body.permissions)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                HttpError::unique_constraint_violation(ErrorMessage::RoleExists.to_string())
+            }
+            sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+                HttpError::bad_request(ErrorMessage::UnknownPermission.to_string())
+            }
+            e => {
+                tracing::error!("创建角色失败: {}", e);
+                HttpError::server_error(e.to_string())
+            }
+        })?;
+
+    // -- 角色可能在创建前被查询过，清除缓存的空权限
+    app_state.permission_resolver.invalidate(&role.name);
+
+    tracing::info!(
+        "用户 {} 创建了角色 {}: {:?}",
+        auth.id,
+        role.name,
+        role.permissions
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(RoleResponseDto {
+            status: "success".to_string(),
+            role,
+        }),
+    ))
+}
+
+/// 修改角色的描述或权限 -- 管理员角色的权限不能修改，避免所有人失去管理权限；
+/// 调用者不能修改自己的角色，也不能授予自己没有的权限，防止借此提升权限
+pub async fn update_role(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Path(name): Path<String>,
+    Json(body): Json<UpdateRoleDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if name == ADMIN_ROLE && body.permissions.is_some() {
+        return Err(HttpError::new(
+            ErrorMessage::SystemRoleProtected.to_string(),
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if name == auth.role {
+        return Err(permission_denied());
+    }
+
+    if let Some(permissions) = &body.permissions {
+        ensure_grantable(&auth, permissions)?;
+    }
+
+    let role = app_state
+        .db_client
+        .update_role(
+            &name,
+            body.description.as_deref().map(str::trim),
+            body.permissions.as_deref(),
+        )
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+                HttpError::bad_request(ErrorMessage::UnknownPermission.to_string())
+            }
+            e => {
+                tracing::error!("修改角色失败: {}", e);
+                HttpError::server_error(e.to_string())
+            }
+        })?
+        .ok_or_else(role_not_found)?;
+
+    app_state.permission_resolver.invalidate(&role.name);
+
+    tracing::info!(
+        "用户 {} 修改了角色 {}: {:?}",
+        auth.id,
+        role.name,
+        role.permissions
+    );
+
+    Ok(Json(RoleResponseDto {
+        status: "success".to_string(),
+        role,
+    }))
+}
+
+/// 删除自定义角色 -- 内置角色和仍有用户使用的角色不能删除
+pub async fn delete_role(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, HttpError> {
+    let role = app_state
+        .db_client
+        .get_role(&name)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(role_not_found)?;
+
+    if role.is_system {
+        return Err(HttpError::new(
+            ErrorMessage::SystemRoleProtected.to_string(),
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    let deleted = app_state
+        .db_client
+        .delete_role(&name)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+                HttpError::unique_constraint_violation(ErrorMessage::RoleInUse.to_string())
+            }
+            e => {
+                tracing::error!("删除角色失败: {}", e);
+                HttpError::server_error(e.to_string())
+            }
+        })?;
+
+    if !deleted {
+        return Err(role_not_found());
+    }
+
+    app_state.permission_resolver.invalidate(&name);
+
+    tracing::info!("用户 {} 删除了角色 {}", auth.id, name);
+
+    Ok(Json(Response {
+        status: "success",
+        message: "Role deleted".to_string(),
+    }))
+}
+
+/// 只能授予调用者自己拥有的权限
+fn ensure_grantable(auth: &AuthUser, permissions: &[String]) -> Result<(), HttpError> {
+    if permissions.iter().all(|p| auth.permissions.contains(p)) {
+        Ok(())
+    } else {
+        Err(permission_denied())
+    }
+}
+
+fn permission_denied() -> HttpError {
+    HttpError::new(
+        ErrorMessage::PermissionDenied.to_string(),
+        StatusCode::FORBIDDEN,
+    )
+}
+
+fn role_not_found() -> HttpError {
+    HttpError::new(
+        ErrorMessage::RoleNotFound.to_string(),
+        StatusCode::NOT_FOUND,
+    )
+}
@@ -1,60 +1,110 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::Query,
+    extract::{Path, Query},
+    http::StatusCode,
     middleware,
     response::IntoResponse,
-    routing::{get, put},
+    routing::{delete, get, post, put},
     Extension, Json, Router,
 };
+use serde_json::json;
 use validator::Validate;
 
 use crate::{
-    db::UserExt,
+    db::{RecoveryCodeExt, SessionExt, UserExt},
     dtos::{
-        FilterUserDto, NameUpdateDto, RequestQueryDto, Response, RoleUpdateDto, UserData,
-        UserListResponseDto, UserPasswordUpdateDto, UserResponseDto,
+        DeleteAccountDto, FilterUserDto, NameUpdateDto, NewPasswordDto, RequestQueryDto, Response,
+        UserData, UserListResponseDto, UserPasswordUpdateDto, UserResponseDto,
     },
     error::{ErrorMessage, HttpError},
-    middleware::{role_check, JWTAuthMiddleware},
-    models::UserRole,
+    handlers::{
+        admin::{db_error, record_audit_event},
+        api_keys::api_keys_handler,
+        auth::{clear_auth_cookie_headers, ensure_password_not_reused},
+        exports::request_data_export,
+        mfa::mfa_handler,
+        webauthn::passkeys_handler,
+    },
+    mail::mails::send_account_deletion_email,
+    middleware::{guard::RequirePermission, require_session, AuthUser},
+    models::{AuditAction, Permission, User},
     utils::password,
     AppState,
 };
 
 pub fn users_handler() -> Router {
     Router::new()
+        // -- 个人账户接口：所有已登录用户均可访问
         .route(
             "/me",
-            get(get_me).layer(middleware::from_fn(|state, req, next| {
-                role_check(state, req, next, vec![UserRole::Admin, UserRole::User])
-            })),
+            get(get_me).merge(delete(delete_me).layer(middleware::from_fn(require_session))),
         )
         .route(
-            "/users",
-            get(get_users).layer(middleware::from_fn(|state, req, next| {
-                role_check(state, req, next, vec![UserRole::Admin])
-            })),
+            "/me/export",
+            post(request_data_export).layer(middleware::from_fn(require_session)),
         )
         .route("/name", put(update_user_name))
-        .route("/role", put(update_user_role))
-        .route("/password", put(update_user_password))
+        .route(
+            "/password",
+            put(update_user_password).layer(middleware::from_fn(require_session)),
+        )
+        .nest(
+            "/mfa",
+            mfa_handler().layer(middleware::from_fn(require_session)),
+        )
+        .nest(
+            "/passkeys",
+            passkeys_handler().layer(middleware::from_fn(require_session)),
+        )
+        .nest(
+            "/api-keys",
+            api_keys_handler().layer(middleware::from_fn(require_session)),
+        )
+        .layer(RequirePermission::new(Permission::ManageOwnAccount))
+        // -- 管理接口：按所需权限单独声明
+        .route(
+            "/users",
+            get(get_users).layer(RequirePermission::new(Permission::ListUsers)),
+        )
+        .route(
+            "/{user_id}/unlock",
+            post(unlock_user).layer(RequirePermission::new(Permission::ManageUsers)),
+        )
+        .route(
+            "/{user_id}/force-password-change",
+            post(force_password_change)
+                .delete(cancel_password_change)
+                .layer(RequirePermission::new(Permission::ManageUsers)),
+        )
 }
 
 pub async fn get_me(
-    Extension(_app_state): Extension<Arc<AppState>>,
-    Extension(user): Extension<JWTAuthMiddleware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
 ) -> Result<impl IntoResponse, HttpError> {
-    let filtered_user = FilterUserDto::filter_user(&user.user);
+    let user = auth.user(&app_state).await?;
+    let filtered_user = FilterUserDto::filter_user(&user);
+
+    // -- 返回剩余恢复码数量，便于前端提醒用户及时重新生成
+    let recovery_codes_remaining = app_state
+        .db_client
+        .count_unused_recovery_codes(user.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("统计恢复码失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
 
     let response_data = UserResponseDto {
         status: "success".to_string(),
         data: UserData {
             user: filtered_user,
+            recovery_codes_remaining: Some(recovery_codes_remaining),
         },
     };
 
-    tracing::info!("成功获取用户信息: {}", user.user.email);
+    tracing::info!("成功获取用户信息: {}", user.email);
     Ok(Json(response_data))
 }
 
@@ -98,10 +148,10 @@ pub async fn get_users(
 
 pub async fn update_user_name(
     Extension(app_state): Extension<Arc<AppState>>,
-    Extension(user): Extension<JWTAuthMiddleware>,
+    auth: AuthUser,
     Json(body): Json<NameUpdateDto>,
 ) -> Result<impl IntoResponse, HttpError> {
-    tracing::info!("更新用户名，用户ID: {}, 新用户名: {}", user.user.id, body.name);
+    tracing::info!("更新用户名，用户ID: {}, 新用户名: {}", auth.id, body.name);
     
     body.validate()
         .map_err(|e| {
@@ -109,12 +159,9 @@ pub async fn update_user_name(
             HttpError::bad_request(e.to_string())
         })?;
 
-    let user = &user.user;
-    let user_id = uuid::Uuid::parse_str(&user.id.to_string()).unwrap();
-
     let result = app_state
         .db_client
-        .update_user_name(user_id, &body.name)
+        .update_user_name(auth.id, &body.name)
         .await
         .map_err(|e| {
             tracing::error!("更新用户名失败: {}", e);
@@ -126,6 +173,7 @@ pub async fn update_user_name(
     let response = UserResponseDto {
         data: UserData {
             user: filtered_user,
+            recovery_codes_remaining: None,
         },
         status: "success".to_string(),
     };
@@ -133,107 +181,272 @@ pub async fn update_user_name(
     Ok(Json(response))
 }
 
-pub async fn update_user_role(
+pub async fn update_user_password(
     Extension(app_state): Extension<Arc<AppState>>,
-    Extension(user): Extension<JWTAuthMiddleware>,
-    Json(body): Json<RoleUpdateDto>,
+    auth: AuthUser,
+    Json(body): Json<UserPasswordUpdateDto>,
 ) -> Result<impl IntoResponse, HttpError> {
-    body.validate()
+    tracing::info!("更新用户密码，用户ID: {}", auth.id);
+
+    let user = auth.user(&app_state).await?;
+    let user_id = user.id;
+
+    body.validate_with_policy(&app_state.env.password_policy, &[&user.name, &user.email])
         .map_err(|e| {
-            tracing::warn!("角色更新请求验证失败: {}", e);
+            tracing::warn!("密码更新请求验证失败: {}", e);
             HttpError::bad_request(e.to_string())
         })?;
 
-    if user.user.role != UserRole::Admin {
-        tracing::warn!(
-            "权限不足，用户: {} 尝试更新角色但不是管理员",
-            user.user.email
-        );
-        return Err(HttpError::unauthorized(
-            ErrorMessage::PermissionDenied.to_string(),
+    let password_match = password::compare(
+        &body.old_password,
+        &user.password,
+        &app_state.env.password_hash,
+    )
+    .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !password_match {
+        tracing::warn!("当前密码不匹配，用户ID: {}", user.id);
+        return Err(HttpError::bad_request(
+            ErrorMessage::WrongCredentials.to_string(),
         ));
     }
 
-    let user_id = uuid::Uuid::parse_str(&user.user.id.to_string()).unwrap();
+    ensure_password_not_reused(&app_state, &user, &body.new_password).await?;
 
-    let result = app_state
+    let hash_password =
+        password::hash(&body.new_password, &app_state.env.password_hash).map_err(|e| {
+            tracing::error!("密码加密失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    app_state
         .db_client
-        .update_user_role(user_id, body.role)
+        .update_user_password(
+            user_id,
+            hash_password,
+            app_state.env.password_policy.history_size,
+        )
         .await
         .map_err(|e| {
-            tracing::error!("更新用户角色失败: {}", e);
+            tracing::error!("更新密码失败: {}", e);
             HttpError::server_error(e.to_string())
         })?;
 
-    let filtered_user = FilterUserDto::filter_user(&result);
+    // -- 修改密码后吊销所有刷新会话，访问令牌已通过令牌版本失效
+    app_state
+        .db_client
+        .revoke_user_sessions(user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("吊销用户会话失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
 
-    let response = UserResponseDto {
-        data: UserData {
-            user: filtered_user,
-        },
-        status: "success".to_string(),
+    tracing::info!("密码更新成功，用户ID: {}", user.id);
+    
+    let response = Response {
+        message: "Password updated Successfully".to_string(),
+        status: "success",
     };
 
     Ok(Json(response))
 }
 
-pub async fn update_user_password(
+/// 申请删除自己的账户 -- 校验密码后标记删除并吊销所有会话
+///
+/// 宽限期内使用任意方式重新登录会恢复账户，宽限期过后由后台任务彻底删除
+pub async fn delete_me(
     Extension(app_state): Extension<Arc<AppState>>,
-    Extension(user): Extension<JWTAuthMiddleware>,
-    Json(body): Json<UserPasswordUpdateDto>,
+    auth: AuthUser,
+    Json(body): Json<DeleteAccountDto>,
 ) -> Result<impl IntoResponse, HttpError> {
-    tracing::info!("更新用户密码，用户ID: {}", user.user.id);
-    
     body.validate()
-        .map_err(|e| {
-            tracing::warn!("密码更新请求验证失败: {}", e);
-            HttpError::bad_request(e.to_string())
-        })?;
-
-    let user = &user.user;
-    let user_id = uuid::Uuid::parse_str(&user.id.to_string()).unwrap();
-
-    let result = app_state
-        .db_client
-        .get_user(Some(user_id), None, None, None)
-        .await
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
-    let user = result.ok_or(HttpError::unauthorized(
-        ErrorMessage::InvalidToken.to_string(),
-    ))?;
+    let user = auth.user(&app_state).await?;
 
-    let password_match = password::compare(&body.old_password, &user.password)
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+    let password_match =
+        password::compare(&body.password, &user.password, &app_state.env.password_hash)
+            .map_err(|_| HttpError::bad_request(ErrorMessage::WrongCredentials.to_string()))?;
 
     if !password_match {
-        tracing::warn!("当前密码不匹配，用户ID: {}", user.id);
+        tracing::warn!("申请删除账户时密码不匹配，用户ID: {}", user.id);
         return Err(HttpError::bad_request(
             ErrorMessage::WrongCredentials.to_string(),
         ));
     }
 
-    let hash_password = password::hash(&body.new_password)
+    let user = app_state
+        .db_client
+        .request_account_deletion(user.id)
+        .await
+        .map_err(db_error)?;
+
+    app_state
+        .db_client
+        .revoke_user_sessions(user.id)
+        .await
         .map_err(|e| {
-            tracing::error!("密码加密失败: {}", e);
+            tracing::error!("吊销用户会话失败: {}", e);
             HttpError::server_error(e.to_string())
         })?;
 
+    let grace_days = app_state.env.account_deletion_grace_days;
+
+    record_audit_event(
+        &app_state,
+        &auth,
+        user.id,
+        AuditAction::AccountDeletionRequested,
+        json!({ "graceDays": grace_days }),
+    )
+    .await?;
+
+    tracing::info!("用户 {} 申请删除账户，宽限期 {} 天", user.email, grace_days);
+
+    let email = user.email.clone();
+    let name = user.name.clone();
+    tokio::spawn(async move {
+        match send_account_deletion_email(&email, &name, grace_days).await {
+            Ok(_) => tracing::info!("成功发送账户删除确认邮件给用户: {}", email),
+            Err(e) => tracing::error!("发送账户删除确认邮件失败: {}", e),
+        }
+    });
+
+    let mut response = Json(Response {
+        status: "success",
+        message: format!(
+            "Your account will be deleted in {} days, sign in again before then to restore it",
+            grace_days
+        ),
+    })
+    .into_response();
+    response.headers_mut().extend(clear_auth_cookie_headers());
+
+    Ok(response)
+}
+
+/// 管理员解除用户的登录锁定
+pub async fn unlock_user(
+    Extension(app_state): Extension<Arc<AppState>>,
+    admin: AuthUser,
+    Path(user_id): Path<uuid::Uuid>,
+) -> Result<impl IntoResponse, HttpError> {
+    let user = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new("User not found", StatusCode::NOT_FOUND))?;
+
     app_state
         .db_client
-        .update_user_password(user_id, hash_password)
+        .unlock_user(user.id)
         .await
         .map_err(|e| {
-            tracing::error!("更新密码失败: {}", e);
+            tracing::error!("解除登录锁定失败: {}", e);
             HttpError::server_error(e.to_string())
         })?;
 
-    tracing::info!("密码更新成功，用户ID: {}", user.id);
-    
-    let response = Response {
-        message: "Password updated Successfully".to_string(),
+    record_audit_event(
+        &app_state,
+        &admin,
+        user.id,
+        AuditAction::UserUnlocked,
+        json!({}),
+    )
+    .await?;
+
+    tracing::info!("管理员 {} 解除了用户 {} 的登录锁定", admin.id, user.email);
+
+    Ok(Json(Response {
         status: "success",
-    };
+        message: "Account unlocked".to_string(),
+    }))
+}
 
-    Ok(Json(response))
+/// 管理员要求用户下次登录时修改密码 -- 用于怀疑密码泄露的情况，同时吊销该用户的所有会话
+pub async fn force_password_change(
+    Extension(app_state): Extension<Arc<AppState>>,
+    admin: AuthUser,
+    Path(user_id): Path<uuid::Uuid>,
+) -> Result<impl IntoResponse, HttpError> {
+    let user = set_must_change_password(&app_state, user_id, true).await?;
+
+    app_state
+        .db_client
+        .revoke_user_sessions(user.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("吊销用户会话失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    record_audit_event(
+        &app_state,
+        &admin,
+        user.id,
+        AuditAction::PasswordChangeForced,
+        json!({}),
+    )
+    .await?;
+
+    tracing::info!("管理员 {} 要求用户 {} 修改密码", admin.id, user.email);
+
+    Ok(Json(Response {
+        status: "success",
+        message: "User must change password on next login".to_string(),
+    }))
+}
+
+/// 管理员取消用户的修改密码要求 -- 不影响密码过期策略
+pub async fn cancel_password_change(
+    Extension(app_state): Extension<Arc<AppState>>,
+    admin: AuthUser,
+    Path(user_id): Path<uuid::Uuid>,
+) -> Result<impl IntoResponse, HttpError> {
+    let user = set_must_change_password(&app_state, user_id, false).await?;
+
+    record_audit_event(
+        &app_state,
+        &admin,
+        user.id,
+        AuditAction::PasswordChangeCleared,
+        json!({}),
+    )
+    .await?;
+
+    tracing::info!(
+        "管理员 {} 取消了用户 {} 的修改密码要求",
+        admin.id,
+        user.email
+    );
+
+    Ok(Json(Response {
+        status: "success",
+        message: "Password change requirement cleared".to_string(),
+    }))
+}
+
+/// 设置用户的修改密码要求，用户不存在时返回 404
+async fn set_must_change_password(
+    app_state: &AppState,
+    user_id: uuid::Uuid,
+    required: bool,
+) -> Result<User, HttpError> {
+    let user = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::new("User not found", StatusCode::NOT_FOUND))?;
+
+    app_state
+        .db_client
+        .set_must_change_password(user.id, required)
+        .await
+        .map_err(|e| {
+            tracing::error!("更新修改密码要求失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })
 }
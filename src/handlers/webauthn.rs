@@ -0,0 +1,446 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Path,
+    middleware,
+    response::IntoResponse,
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use chrono::{Duration, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+use webauthn_rs::prelude::{
+    AuthenticationResult, CredentialID, DiscoverableAuthentication, DiscoverableKey, Passkey,
+    PasskeyAuthentication, PasskeyRegistration,
+};
+
+use crate::{
+    db::{UserExt, WebauthnExt},
+    dtos::{
+        FilterPasskeyDto, PasskeyAuthStartResponseDto, PasskeyListResponseDto,
+        PasskeyLoginFinishDto, PasskeyMfaFinishDto, PasskeyMfaStartDto, PasskeyRegisterFinishDto,
+        PasskeyRegisterStartResponseDto, Response,
+    },
+    error::{ErrorMessage, HttpError},
+    handlers::auth::{check_mfa_token, consume_mfa_token, login_response},
+    middleware::{auth, require_session, AuthUser},
+    models::{WebauthnCeremony, WebauthnCredential},
+    AppState,
+};
+
+/// 通行密钥登录与两步验证路由 -- 挂载在 `/api/auth/webauthn` 下
+pub fn webauthn_handler() -> Router {
+    Router::new()
+        .route(
+            "/register/start",
+            post(start_registration)
+                .layer(middleware::from_fn(require_session))
+                .layer(middleware::from_fn(auth)),
+        )
+        .route(
+            "/register/finish",
+            post(finish_registration)
+                .layer(middleware::from_fn(require_session))
+                .layer(middleware::from_fn(auth)),
+        )
+        .route("/login/start", post(start_login))
+        .route("/login/finish", post(finish_login))
+        .route("/mfa/start", post(start_mfa))
+        .route("/mfa/finish", post(finish_mfa))
+}
+
+/// 通行密钥管理路由 -- 挂载在 `/api/users/passkeys` 下
+pub fn passkeys_handler() -> Router {
+    Router::new()
+        .route("/", get(get_passkeys))
+        .route("/{id}", delete(delete_passkey))
+}
+
+/// 仪式挑战有效期（分钟）
+const CHALLENGE_MAXAGE: i64 = 5;
+
+/// 保存仪式的服务端状态，返回挑战ID供客户端在完成仪式时回传
+async fn save_challenge<T: Serialize>(
+    app_state: &AppState,
+    user_id: Option<Uuid>,
+    ceremony: WebauthnCeremony,
+    state: &T,
+) -> Result<Uuid, HttpError> {
+    let state = serde_json::to_value(state).map_err(|e| HttpError::server_error(e.to_string()))?;
+    let expires_at = Utc::now() + Duration::minutes(CHALLENGE_MAXAGE);
+
+    let challenge = app_state
+        .db_client
+        .save_webauthn_challenge(user_id, ceremony, state, expires_at)
+        .await
+        .map_err(|e| {
+            tracing::error!("保存 WebAuthn 挑战失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    Ok(challenge.id)
+}
+
+/// 取出仪式的服务端状态 -- 挑战只能使用一次，且必须属于发起仪式的用户
+///
+/// # 错误类型
+/// - `InvalidChallenge` -- 挑战不存在、已使用、已过期或不属于该用户
+async fn take_challenge<T: DeserializeOwned>(
+    app_state: &AppState,
+    challenge_id: Uuid,
+    ceremony: WebauthnCeremony,
+    user_id: Option<Uuid>,
+) -> Result<T, HttpError> {
+    let invalid_challenge = || HttpError::bad_request(ErrorMessage::InvalidChallenge.to_string());
+
+    let challenge = app_state
+        .db_client
+        .take_webauthn_challenge(challenge_id, ceremony)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(invalid_challenge)?;
+
+    if challenge.expires_at < Utc::now() || challenge.user_id != user_id {
+        return Err(invalid_challenge());
+    }
+
+    serde_json::from_value(challenge.state).map_err(|e| HttpError::server_error(e.to_string()))
+}
+
+/// 反序列化数据库中保存的通行密钥
+fn parse_passkey(credential: &WebauthnCredential) -> Result<Passkey, HttpError> {
+    serde_json::from_value(credential.passkey.clone())
+        .map_err(|e| HttpError::server_error(e.to_string()))
+}
+
+/// 认证成功后更新凭证 -- 保存新的签名计数器并记录使用时间
+async fn record_passkey_use(
+    app_state: &AppState,
+    credential: &WebauthnCredential,
+    mut passkey: Passkey,
+    result: &AuthenticationResult,
+) -> Result<(), HttpError> {
+    passkey.update_credential(result);
+    let passkey =
+        serde_json::to_value(&passkey).map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    app_state
+        .db_client
+        .update_webauthn_credential(credential.id, passkey)
+        .await
+        .map_err(|e| {
+            tracing::error!("更新通行密钥失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })
+}
+
+/// 通行密钥校验失败时统一返回的错误
+fn verification_failed(e: impl std::fmt::Display) -> HttpError {
+    tracing::warn!("通行密钥校验失败: {}", e);
+    HttpError::unauthorized(ErrorMessage::PasskeyVerificationFailed.to_string())
+}
+
+/// 开始注册通行密钥 -- 返回浏览器 `navigator.credentials.create()` 所需的参数
+pub async fn start_registration(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<impl IntoResponse, HttpError> {
+    let user = &auth.user(&app_state).await?;
+
+    let credentials = app_state
+        .db_client
+        .get_webauthn_credentials(user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    // -- 排除已注册的凭证，避免同一认证器重复注册
+    let exclude_credentials: Vec<CredentialID> = credentials
+        .iter()
+        .map(|credential| CredentialID::from(credential.credential_id.clone()))
+        .collect();
+
+    let (options, registration) = app_state
+        .webauthn
+        .start_passkey_registration(user.id, &user.email, &user.name, Some(exclude_credentials))
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let challenge_id = save_challenge(
+        &app_state,
+        Some(user.id),
+        WebauthnCeremony::Registration,
+        &registration,
+    )
+    .await?;
+
+    Ok(Json(PasskeyRegisterStartResponseDto {
+        status: "success".to_string(),
+        challenge_id: challenge_id.to_string(),
+        options,
+    }))
+}
+
+/// 完成注册通行密钥 -- 校验认证器返回的凭证并保存公钥
+pub async fn finish_registration(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<PasskeyRegisterFinishDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = &auth.user(&app_state).await?;
+
+    let registration: PasskeyRegistration = take_challenge(
+        &app_state,
+        body.challenge_id,
+        WebauthnCeremony::Registration,
+        Some(user.id),
+    )
+    .await?;
+
+    let passkey = app_state
+        .webauthn
+        .finish_passkey_registration(&body.credential, &registration)
+        .map_err(verification_failed)?;
+
+    let passkey_json =
+        serde_json::to_value(&passkey).map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let result = app_state
+        .db_client
+        .save_webauthn_credential(user.id, passkey.cred_id(), body.name.trim(), passkey_json)
+        .await;
+
+    match result {
+        Ok(_) => {
+            tracing::info!("用户 {} 注册了通行密钥 {}", user.email, body.name.trim());
+            Ok(Json(Response {
+                status: "success",
+                message: "Passkey registered".to_string(),
+            }))
+        }
+        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+            Err(HttpError::unique_constraint_violation(
+                ErrorMessage::PasskeyAlreadyRegistered.to_string(),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("保存通行密钥失败: {}", e);
+            Err(HttpError::server_error(e.to_string()))
+        }
+    }
+}
+
+/// 开始通行密钥登录 -- 使用可发现凭证，无需事先提供邮箱
+pub async fn start_login(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let (options, authentication) = app_state
+        .webauthn
+        .start_discoverable_authentication()
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let challenge_id = save_challenge(
+        &app_state,
+        None,
+        WebauthnCeremony::Authentication,
+        &authentication,
+    )
+    .await?;
+
+    Ok(Json(PasskeyAuthStartResponseDto {
+        status: "success".to_string(),
+        challenge_id: challenge_id.to_string(),
+        options,
+    }))
+}
+
+/// 完成通行密钥登录 -- 通行密钥同时满足持有与用户验证两个因素，直接签发访问令牌
+///
+/// # 返回
+/// - `Ok(Response)` -- 登录成功，返回与登录接口相同的访问令牌和 cookie
+/// - `Err(HttpError)` -- 登录失败
+///   - `BadRequest` -- 挑战无效或已过期
+///   - `Unauthorized` -- 凭证未注册或签名校验失败
+pub async fn finish_login(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<PasskeyLoginFinishDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    let authentication: DiscoverableAuthentication = take_challenge(
+        &app_state,
+        body.challenge_id,
+        WebauthnCeremony::Authentication,
+        None,
+    )
+    .await?;
+
+    let (user_id, credential_id) = app_state
+        .webauthn
+        .identify_discoverable_authentication(&body.credential)
+        .map_err(verification_failed)?;
+
+    let credential = app_state
+        .db_client
+        .get_webauthn_credential_by_credential_id(credential_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .filter(|credential| credential.user_id == user_id)
+        .ok_or_else(|| verification_failed("未注册的凭证"))?;
+
+    let passkey = parse_passkey(&credential)?;
+
+    let result = app_state
+        .webauthn
+        .finish_discoverable_authentication(
+            &body.credential,
+            authentication,
+            &[DiscoverableKey::from(&passkey)],
+        )
+        .map_err(verification_failed)?;
+
+    record_passkey_use(&app_state, &credential, passkey, &result).await?;
+
+    let user = app_state
+        .db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+
+    tracing::info!("用户 {} 使用通行密钥登录", user.email);
+    login_response(&app_state, &user).await
+}
+
+/// 开始通行密钥两步验证 -- 使用登录时返回的 MFA 令牌，令牌在完成验证前不会被消费
+pub async fn start_mfa(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<PasskeyMfaStartDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = check_mfa_token(&app_state, &body.mfa_token).await?;
+
+    let credentials = app_state
+        .db_client
+        .get_webauthn_credentials(user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if credentials.is_empty() {
+        return Err(HttpError::bad_request(
+            ErrorMessage::NoPasskeysRegistered.to_string(),
+        ));
+    }
+
+    let passkeys = credentials
+        .iter()
+        .map(parse_passkey)
+        .collect::<Result<Vec<Passkey>, _>>()?;
+
+    let (options, authentication) = app_state
+        .webauthn
+        .start_passkey_authentication(&passkeys)
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let challenge_id = save_challenge(
+        &app_state,
+        Some(user.id),
+        WebauthnCeremony::Mfa,
+        &authentication,
+    )
+    .await?;
+
+    Ok(Json(PasskeyAuthStartResponseDto {
+        status: "success".to_string(),
+        challenge_id: challenge_id.to_string(),
+        options,
+    }))
+}
+
+/// 完成通行密钥两步验证 -- 校验成功后签发与登录接口相同的访问令牌和 cookie
+pub async fn finish_mfa(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<PasskeyMfaFinishDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = consume_mfa_token(&app_state, &body.mfa_token).await?;
+
+    let authentication: PasskeyAuthentication = take_challenge(
+        &app_state,
+        body.challenge_id,
+        WebauthnCeremony::Mfa,
+        Some(user.id),
+    )
+    .await?;
+
+    let result = app_state
+        .webauthn
+        .finish_passkey_authentication(&body.credential, &authentication)
+        .map_err(verification_failed)?;
+
+    let credential = app_state
+        .db_client
+        .get_webauthn_credential_by_credential_id(result.cred_id())
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .filter(|credential| credential.user_id == user.id)
+        .ok_or_else(|| verification_failed("未注册的凭证"))?;
+
+    let passkey = parse_passkey(&credential)?;
+    record_passkey_use(&app_state, &credential, passkey, &result).await?;
+
+    tracing::info!("用户 {} 使用通行密钥完成两步验证", user.email);
+    login_response(&app_state, &user).await
+}
+
+/// 获取当前用户注册的通行密钥列表
+pub async fn get_passkeys(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<impl IntoResponse, HttpError> {
+    let credentials = app_state
+        .db_client
+        .get_webauthn_credentials(auth.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(PasskeyListResponseDto {
+        status: "success".to_string(),
+        passkeys: FilterPasskeyDto::filter_passkeys(&credentials),
+    }))
+}
+
+/// 删除当前用户的通行密钥
+pub async fn delete_passkey(
+    Extension(app_state): Extension<Arc<AppState>>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, HttpError> {
+    let deleted = app_state
+        .db_client
+        .delete_webauthn_credential(auth.id, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("删除通行密钥失败: {}", e);
+            HttpError::server_error(e.to_string())
+        })?;
+
+    if !deleted {
+        return Err(HttpError::new(
+            "Passkey not found",
+            axum::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    tracing::info!("用户 {} 删除了通行密钥 {}", auth.id, id);
+
+    Ok(Json(Response {
+        status: "success",
+        message: "Passkey deleted".to_string(),
+    }))
+}
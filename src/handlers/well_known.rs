@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use axum::{
+    http::header::CACHE_CONTROL, response::IntoResponse, routing::get, Extension, Json, Router,
+};
+
+use crate::AppState;
+
+/// 公开的发现端点 -- 挂载在根路径的 `/.well-known` 下
+pub fn well_known_handler() -> Router {
+    Router::new().route("/jwks.json", get(get_jwks))
+}
+
+/// JWKS 缓存时间（秒） -- 轮换密钥时新公钥需要提前发布至少这么久
+const JWKS_MAX_AGE: u32 = 300;
+
+/// 获取验证访问令牌的公钥集合，其他服务可以据此按 `kid` 验证令牌而无需共享密钥
+pub async fn get_jwks(Extension(app_state): Extension<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(CACHE_CONTROL, format!("public, max-age={}", JWKS_MAX_AGE))],
+        Json(app_state.jwt_keys.jwks().clone()),
+    )
+}
@@ -0,0 +1,117 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use serde_json::json;
+
+use crate::{
+    db::{AuditEventExt, DataExportExt, UserExt},
+    models::{AuditAction, UserStatus},
+    AppState,
+};
+
+/// 检查到期暂停的间隔
+const LIFT_SUSPENSIONS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 清理宽限期已过的待删除账户的间隔
+const PURGE_DELETED_ACCOUNTS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// 清理过期的个人数据导出的间隔
+const PURGE_DATA_EXPORTS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// 启动后台任务 -- 多实例部署时每个实例都会执行，任务本身是幂等的
+pub fn spawn_background_jobs(app_state: Arc<AppState>) {
+    tokio::spawn(lift_expired_suspensions(app_state.clone()));
+    tokio::spawn(purge_deleted_accounts(app_state.clone()));
+    tokio::spawn(purge_expired_data_exports(app_state));
+}
+
+/// 定期将暂停已到期的账户恢复为正常状态
+///
+/// 到期后即使任务尚未执行，登录和认证也已将账户视为正常，任务只负责更新状态并记录审计事件
+async fn lift_expired_suspensions(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(LIFT_SUSPENSIONS_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let user_ids = match app_state.db_client.lift_expired_suspensions().await {
+            Ok(user_ids) => user_ids,
+            Err(e) => {
+                tracing::error!("恢复到期暂停的账户失败: {}", e);
+                continue;
+            }
+        };
+
+        for user_id in user_ids {
+            tracing::info!("用户 {} 的暂停已到期，恢复为正常状态", user_id);
+
+            if let Err(e) = app_state
+                .db_client
+                .record_audit_event(
+                    None,
+                    Some(user_id),
+                    AuditAction::StatusChanged,
+                    json!({
+                        "from": UserStatus::Suspended,
+                        "to": UserStatus::Active,
+                        "reason": "suspension expired",
+                    }),
+                )
+                .await
+            {
+                tracing::error!("记录审计事件失败: {}", e);
+            }
+        }
+    }
+}
+
+/// 定期彻底删除宽限期已过的待删除账户
+///
+/// 用户的关联数据随账户一起删除，审计事件只保留不含个人信息的部分
+async fn purge_deleted_accounts(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(PURGE_DELETED_ACCOUNTS_INTERVAL);
+    let grace_period = chrono::Duration::days(app_state.env.account_deletion_grace_days);
+
+    loop {
+        interval.tick().await;
+
+        let user_ids = match app_state
+            .db_client
+            .purge_deleted_users(Utc::now() - grace_period)
+            .await
+        {
+            Ok(user_ids) => user_ids,
+            Err(e) => {
+                tracing::error!("清理待删除账户失败: {}", e);
+                continue;
+            }
+        };
+
+        for user_id in user_ids {
+            tracing::info!("用户 {} 的删除宽限期已过，账户已彻底删除", user_id);
+
+            if let Err(e) = app_state
+                .db_client
+                .record_audit_event(None, Some(user_id), AuditAction::AccountPurged, json!({}))
+                .await
+            {
+                tracing::error!("记录审计事件失败: {}", e);
+            }
+        }
+    }
+}
+
+/// 定期删除下载链接已过期的个人数据导出
+async fn purge_expired_data_exports(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(PURGE_DATA_EXPORTS_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match app_state.db_client.delete_expired_data_exports().await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("已删除 {} 个过期的个人数据导出", count),
+            Err(e) => tracing::error!("清理过期的个人数据导出失败: {}", e),
+        }
+    }
+}
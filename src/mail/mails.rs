@@ -47,3 +47,107 @@ pub async fn send_forgot_password_email(
 
     send_email(to_email, subject, template_path, &placeholders).await
 }
+
+pub async fn send_magic_link_email(
+    to_email: &str,
+    username: &str,
+    token: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let subject = "Your sign-in link";
+    let template_path = "src/mail/templates/MagicLink-email.html";
+    let config = Config::from_env();
+    let base_url = format!(
+        "http://localhost:{}/api/auth/magic-link/verify",
+        config.server_port
+    );
+    let login_link = create_verification_link(&base_url, token);
+    let placeholders = vec![
+        ("{{username}}".to_string(), username.to_string()),
+        ("{{login_link}}".to_string(), login_link),
+    ];
+
+    send_email(to_email, subject, template_path, &placeholders).await
+}
+
+pub async fn send_account_locked_email(
+    to_email: &str,
+    username: &str,
+    token: &str,
+    lockout_minutes: i64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let subject = "Your account has been temporarily locked";
+    let template_path = "src/mail/templates/AccountLocked-email.html";
+    let config = Config::from_env();
+    let base_url = format!("http://localhost:{}/api/auth/unlock", config.server_port);
+    let unlock_link = create_verification_link(&base_url, token);
+    let placeholders = vec![
+        ("{{username}}".to_string(), username.to_string()),
+        ("{{unlock_link}}".to_string(), unlock_link),
+        (
+            "{{lockout_minutes}}".to_string(),
+            lockout_minutes.to_string(),
+        ),
+    ];
+
+    send_email(to_email, subject, template_path, &placeholders).await
+}
+
+pub async fn send_registration_attempt_email(
+    to_email: &str,
+    username: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let subject = "Someone tried to register with your email";
+    let template_path = "src/mail/templates/RegistrationAttempt-email.html";
+    let config = Config::from_env();
+    let reset_link = format!(
+        "{}/forgot-password",
+        config.frontend_url.trim_end_matches('/')
+    );
+    let placeholders = vec![
+        ("{{username}}".to_string(), username.to_string()),
+        ("{{reset_link}}".to_string(), reset_link),
+    ];
+
+    send_email(to_email, subject, template_path, &placeholders).await
+}
+
+pub async fn send_account_deletion_email(
+    to_email: &str,
+    username: &str,
+    grace_days: i64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let subject = "Your account is scheduled for deletion";
+    let template_path = "src/mail/templates/AccountDeletion-email.html";
+    let config = Config::from_env();
+    let login_link = format!("{}/login", config.frontend_url.trim_end_matches('/'));
+    let placeholders = vec![
+        ("{{username}}".to_string(), username.to_string()),
+        ("{{login_link}}".to_string(), login_link),
+        ("{{grace_days}}".to_string(), grace_days.to_string()),
+    ];
+
+    send_email(to_email, subject, template_path, &placeholders).await
+}
+
+pub async fn send_data_export_email(
+    to_email: &str,
+    username: &str,
+    token: &str,
+    expiry_hours: i64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let subject = "Your personal data export is ready";
+    let template_path = "src/mail/templates/DataExport-email.html";
+    let config = Config::from_env();
+    let base_url = format!(
+        "http://localhost:{}/api/exports/download",
+        config.server_port
+    );
+    let download_link = create_verification_link(&base_url, token);
+    let placeholders = vec![
+        ("{{username}}".to_string(), username.to_string()),
+        ("{{download_link}}".to_string(), download_link),
+        ("{{expiry_hours}}".to_string(), expiry_hours.to_string()),
+    ];
+
+    send_email(to_email, subject, template_path, &placeholders).await
+}
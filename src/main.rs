@@ -5,13 +5,17 @@ mod db;
 mod dtos;
 mod error;
 mod handlers;
+mod jobs;
 mod mail;
 mod middleware;
 mod models;
+mod oauth;
+mod rate_limit;
+mod rbac;
 mod routes;
 mod utils;
 
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use axum::{
     http::{
@@ -23,15 +27,24 @@ use axum::{
 use config::Config;
 use db::DBClient;
 use dotenvy::dotenv;
+use oauth::IdentityProviders;
+use rate_limit::{memory::InMemoryRateLimitStore, RateLimitStore};
+use rbac::PermissionResolver;
 use routes::create_router;
 use sqlx::postgres::PgPoolOptions;
 use tower_http::cors::CorsLayer;
-use utils::init_production_logging;
+use utils::{init_production_logging, jwt_keys::JwtKeys};
+use webauthn_rs::prelude::{Url, Webauthn, WebauthnBuilder};
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub env: Config,
     pub db_client: DBClient,
+    pub jwt_keys: Arc<JwtKeys>,
+    pub webauthn: Arc<Webauthn>,
+    pub identity_providers: IdentityProviders,
+    pub rate_limit_store: Arc<dyn RateLimitStore>,
+    pub permission_resolver: Arc<PermissionResolver>,
 }
 
 #[tokio::main]
@@ -69,24 +82,64 @@ async fn main() {
         .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE])
         // -- 允许跨域请求中包含 认证信息（如 cookies）
         .allow_credentials(true)
-        // -- 允许使用 GET、 POST 和 PUT 这些 HTTP 请求方法
-        .allow_methods([Method::GET, Method::POST, Method::PUT]);
+        // -- 允许使用 GET、 POST、 PUT、 PATCH 和 DELETE 这些 HTTP 请求方法
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ]);
+
+    // -- 加载 JWT 签名密钥和验证密钥，密钥文件无效时直接退出
+    let jwt_keys = JwtKeys::from_config(&config.jwt).expect("Invalid JWT key configuration");
+
+    // -- 初始化 WebAuthn 依赖方，配置错误时直接退出
+    let rp_origin =
+        Url::parse(&config.webauthn_rp_origin).expect("WEBAUTHN_RP_ORIGIN must be a valid URL");
+    let webauthn = WebauthnBuilder::new(&config.webauthn_rp_id, &rp_origin)
+        .and_then(|builder| builder.rp_name(&config.webauthn_rp_name).build())
+        .expect("Invalid WebAuthn configuration");
+
+    // -- 初始化第三方登录提供方，端点在首次登录时才会自动发现
+    let identity_providers =
+        IdentityProviders::from_config(&config).expect("Invalid OIDC provider configuration");
 
     // -- 初始化数据库客户端连接
     let db_client = DBClient::new(pool);
-    // -- 创建应用程序状态，包含 环境配置 和 数据库客户端
+    // -- 角色权限解析器，按角色缓存权限
+    let permission_resolver = PermissionResolver::new(
+        db_client.clone(),
+        Duration::from_secs(config.permission_cache_ttl_seconds),
+    );
+    // -- 创建应用程序状态，包含 环境配置、 数据库客户端、 JWT 密钥、 WebAuthn 依赖方、 第三方登录提供方、 限流存储 和 权限解析器
     let app_state = AppState {
         env: config.clone(),
         db_client,
+        jwt_keys: Arc::new(jwt_keys),
+        webauthn: Arc::new(webauthn),
+        identity_providers,
+        rate_limit_store: Arc::new(InMemoryRateLimitStore::new()),
+        permission_resolver: Arc::new(permission_resolver),
     };
 
     // -- 使用 Arc 包装 app_state 实现线程安全的共享引用，使多个并发请求可以安全地访问应用状态
-    let app = create_router(Arc::new(app_state.clone())).layer(cors);
+    let app_state = Arc::new(app_state);
+
+    // -- 启动后台任务（恢复到期暂停的账户）
+    jobs::spawn_background_jobs(app_state.clone());
+
+    let app = create_router(app_state).layer(cors);
 
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", &config.server_port))
         .await
         .unwrap();
 
     tracing::info!("Server running on port {}", config.server_port);
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
@@ -1,6 +1,8 @@
+pub mod guard;
+
 use axum::{
-    extract::Request,
-    http::{header, Method, StatusCode, Uri},
+    extract::{FromRequestParts, OriginalUri, Request},
+    http::{header, request::Parts, HeaderMap, Method, StatusCode, Uri},
     middleware::Next,
     response::{IntoResponse, Response},
     Extension,
@@ -9,19 +11,117 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use axum_extra::extract::cookie::CookieJar;
-use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
-    db::UserExt,
+    db::{ApiKeyExt, SessionExt, UserExt},
     error::{ErrorMessage, HttpError},
-    models::{User, UserRole},
-    utils::token,
+    models::{ApiKey, ApiKeyScope, Permission, User, UserStatus},
+    rbac::PermissionSet,
+    utils::token::{self, TokenScope},
     AppState,
 };
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct JWTAuthMiddleware {
-    pub user: User,
+/// 修改密码接口的完整路径 -- 修改密码令牌只能访问该接口
+const PASSWORD_CHANGE_PATH: &str = "/api/users/password";
+
+/// 开启 `TRUST_TOKEN_CLAIMS_ON_READ` 时可以直接信任令牌声明的接口（完整路径）
+///
+/// 只能加入不需要额外权限、没有副作用、并通过 `AuthUser::user()` 加载用户的只读接口，
+/// 其余请求（包括全部管理接口）始终查询数据库校验令牌和账户状态
+const TRUSTED_READ_PATHS: &[&str] = &["/api/users/me"];
+
+/// 已认证的用户 -- 由认证中间件写入请求扩展，处理函数直接将其作为提取器使用
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: Uuid,
+    pub role: String,
+    /// 角色拥有的权限 -- 由认证中间件通过权限解析器加载
+    pub permissions: PermissionSet,
+    /// 登录会话（会话族）ID，通过 API 密钥认证时为空
+    pub session_id: Option<Uuid>,
+    pub token_version: i32,
+    /// 通过 API 密钥认证时为对应的密钥，通过登录会话认证时为空
+    pub api_key: Option<ApiKey>,
+    /// 认证时从数据库加载的用户 -- 信任令牌声明的只读请求中为空
+    user: Option<User>,
+}
+
+impl AuthUser {
+    fn from_user(user: User, session_id: Option<Uuid>, api_key: Option<ApiKey>) -> Self {
+        AuthUser {
+            id: user.id,
+            role: user.role.clone(),
+            permissions: PermissionSet::default(),
+            session_id,
+            token_version: user.token_version,
+            api_key,
+            user: Some(user),
+        }
+    }
+
+    /// 角色是否拥有指定权限
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions.contains(permission.to_str())
+    }
+
+    /// 获取完整的用户信息 -- 认证时已加载则直接返回，信任令牌声明的请求按需查询数据库
+    ///
+    /// 按需加载时补做中间件跳过的令牌版本和账户状态检查
+    pub async fn user(&self, app_state: &AppState) -> Result<User, HttpError> {
+        if let Some(user) = &self.user {
+            return Ok(user.clone());
+        }
+
+        let user = app_state
+            .db_client
+            .get_user(Some(self.id), None, None)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?
+            .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+
+        if user.token_version != self.token_version {
+            return Err(HttpError::unauthorized(
+                ErrorMessage::InvalidToken.to_string(),
+            ));
+        }
+
+        ensure_active(&user)?;
+
+        Ok(user)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = HttpError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .cloned()
+            .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNotAuthenticated.to_string()))
+    }
+}
+
+/// 从 cookie 或 `Authorization: Bearer` 请求头中读取访问令牌
+pub fn extract_access_token(cookie_jar: &CookieJar, headers: &HeaderMap) -> Option<String> {
+    cookie_jar
+        .get("token")
+        .map(|cookie| cookie.value().to_string())
+        .or_else(|| extract_bearer_token(headers))
+}
+
+/// 从 `Authorization: Bearer` 请求头中读取令牌
+fn extract_bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|auth_header| auth_header.to_str().ok())
+        .and_then(|auth_value| {
+            auth_value
+                .strip_prefix("Bearer ")
+                .map(|token| token.to_owned())
+        })
 }
 
 pub async fn auth(
@@ -30,22 +130,69 @@ pub async fn auth(
     mut req: Request,
     next: Next,
 ) -> Result<impl IntoResponse, HttpError> {
-    let cookies = cookie_jar
-        .get("token")
-        .map(|cookie| cookie.value().to_string())
-        .or_else(|| {
-            req.headers()
-                .get(header::AUTHORIZATION)
-                .and_then(|auth_header| auth_header.to_str().ok())
-                .and_then(|auth_value| {
-                    auth_value.strip_prefix("Bearer ").map(|token| token.to_owned())
-                })
-        });
+    // -- 带有 API 密钥前缀的 Bearer 令牌按 API 密钥认证，其余按访问令牌认证
+    let api_key = extract_bearer_token(req.headers())
+        .filter(|token| token.starts_with(token::API_KEY_PREFIX));
+
+    let mut auth_user = match api_key {
+        Some(api_key) => authenticate_api_key(&app_state, &api_key, req.method()).await?,
+        None => {
+            let token = extract_access_token(&cookie_jar, req.headers()).ok_or_else(|| {
+                HttpError::unauthorized(ErrorMessage::TokenNotProvided.to_string())
+            })?;
+
+            let allow_password_change = is_password_change(&req);
+            let trust_claims = app_state.env.trust_token_claims_on_read && is_trusted_read(&req);
 
-    let token = cookies
-        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::TokenNotProvided.to_string()))?;
+            authenticate_access_token(&app_state, token, allow_password_change, trust_claims)
+                .await?
+        }
+    };
+
+    // -- 加载角色拥有的权限，供路由守卫检查
+    auth_user.permissions = app_state
+        .permission_resolver
+        .permissions(&auth_user.role)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    let token_details = match token::decode_token(token, app_state.env.jwt_secret.as_bytes()) {
+    req.extensions_mut().insert(auth_user);
+
+    // -- 通过 Ok 包装异步执行下一个处理器的结果，将请求传递给路由处理函数继续处理
+    Ok(next.run(req).await)
+}
+
+/// 请求的完整路径 -- 嵌套路由中 `req.uri()` 不含前缀，需要使用原始路径
+fn original_path(req: &Request) -> &str {
+    req.extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.0.path())
+        .unwrap_or_else(|| req.uri().path())
+}
+
+/// 请求是否为修改密码
+fn is_password_change(req: &Request) -> bool {
+    req.method() == Method::PUT && original_path(req) == PASSWORD_CHANGE_PATH
+}
+
+/// 请求是否为可以信任令牌声明的只读接口
+fn is_trusted_read(req: &Request) -> bool {
+    matches!(*req.method(), Method::GET | Method::HEAD)
+        && TRUSTED_READ_PATHS.contains(&original_path(req))
+}
+
+/// 校验访问令牌 -- 检查作用域、黑名单与令牌版本，返回令牌所属用户
+///
+/// `allow_password_change` 为 `true` 时同时接受修改密码令牌。
+/// `trust_claims` 为 `true` 时（开启 `TRUST_TOKEN_CLAIMS_ON_READ` 且请求为 `TRUSTED_READ_PATHS` 中的只读接口）
+/// 只校验签名与有效期，直接使用令牌中的声明，不查询数据库。
+async fn authenticate_access_token(
+    app_state: &AppState,
+    token: String,
+    allow_password_change: bool,
+    trust_claims: bool,
+) -> Result<AuthUser, HttpError> {
+    let token_details = match token::decode_token(token, &app_state.jwt_keys) {
         Ok(token_details) => token_details,
         Err(_) => {
             return Err(HttpError::unauthorized(
@@ -54,43 +201,165 @@ pub async fn auth(
         }
     };
 
-    let user_id = uuid::Uuid::parse_str(&token_details.to_string())
+    // -- 只有完整的访问令牌才能访问受保护的路由，修改密码令牌只能用于修改密码
+    match token_details.scope {
+        TokenScope::Access => {}
+        TokenScope::PasswordChange if allow_password_change => {}
+        TokenScope::PasswordChange => {
+            return Err(HttpError::new(
+                ErrorMessage::PasswordChangeRequired.to_string(),
+                StatusCode::FORBIDDEN,
+            ));
+        }
+        TokenScope::MfaPending => {
+            return Err(HttpError::unauthorized(
+                ErrorMessage::InvalidToken.to_string(),
+            ));
+        }
+    }
+
+    let user_id = uuid::Uuid::parse_str(&token_details.sub)
+        .map_err(|_| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
+    let session_id = match &token_details.sid {
+        Some(sid) => Some(
+            Uuid::parse_str(sid)
+                .map_err(|_| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?,
+        ),
+        None => None,
+    };
+
+    // -- 信任令牌声明时不检查黑名单，退出登录后旧令牌在这些接口中仍然有效，直到自然过期；
+    // -- 令牌版本和账户状态在 `AuthUser::user()` 加载用户时检查
+    if trust_claims && token_details.scope == TokenScope::Access {
+        return Ok(AuthUser {
+            id: user_id,
+            role: token_details.role,
+            permissions: PermissionSet::default(),
+            session_id,
+            token_version: token_details.ver,
+            api_key: None,
+            user: None,
+        });
+    }
+
+    let jti = uuid::Uuid::parse_str(&token_details.jti)
         .map_err(|_| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
 
+    // -- 检查令牌是否已在退出登录时被加入黑名单
+    let revoked = app_state
+        .db_client
+        .is_token_revoked(jti)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if revoked {
+        return Err(HttpError::unauthorized(
+            ErrorMessage::InvalidToken.to_string(),
+        ));
+    }
+
     let user = app_state
         .db_client
-        .get_user(Some(user_id), None, None, None)
+        .get_user(Some(user_id), None, None)
         .await
         .map_err(|_| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
 
     let user =
         user.ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
 
-    req.extensions_mut()
-        .insert(JWTAuthMiddleware { user: user.clone() });
+    // -- 令牌版本落后说明用户已退出所有设备或修改过密码
+    if token_details.ver != user.token_version {
+        return Err(HttpError::unauthorized(
+            ErrorMessage::InvalidToken.to_string(),
+        ));
+    }
 
-    // -- 通过 Ok 包装异步执行下一个处理器的结果，将请求传递给路由处理函数继续处理
-    Ok(next.run(req).await)
+    ensure_active(&user)?;
+
+    Ok(AuthUser::from_user(user, session_id, None))
 }
 
-pub async fn role_check(
-    Extension(_app_state): Extension<Arc<AppState>>,
-    req: Request,
-    next: Next,
-    required_roles: Vec<UserRole>,
-) -> Result<impl IntoResponse, HttpError> {
-    let user = req
+/// 校验 API 密钥 -- 检查密钥是否有效以及授权范围是否覆盖请求方法，并记录使用时间
+async fn authenticate_api_key(
+    app_state: &AppState,
+    api_key: &str,
+    method: &Method,
+) -> Result<AuthUser, HttpError> {
+    let api_key = app_state
+        .db_client
+        .get_active_api_key(&token::hash_opaque_token(api_key))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
+
+    // -- 只读密钥只能发起安全方法的请求
+    let required_scope = if method.is_safe() {
+        ApiKeyScope::Read
+    } else {
+        ApiKeyScope::Write
+    };
+
+    if !api_key.has_scope(required_scope) {
+        return Err(HttpError::new(
+            ErrorMessage::InsufficientScope.to_string(),
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    let user = app_state
+        .db_client
+        .get_user(Some(api_key.user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+
+    ensure_active(&user)?;
+
+    // -- 申请删除时令牌版本已递增，API 密钥不受影响，需要单独拦截；账户只能通过重新登录恢复
+    if user.is_pending_deletion() {
+        return Err(HttpError::new(
+            ErrorMessage::AccountPendingDeletion.to_string(),
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    app_state
+        .db_client
+        .touch_api_key(api_key.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(AuthUser::from_user(user, None, Some(api_key)))
+}
+
+/// 拒绝非正常状态的账户 -- 修改状态时令牌版本已递增，这里主要拦截新的登录和不受令牌版本影响的 API 密钥
+pub fn ensure_active(user: &User) -> Result<(), HttpError> {
+    if user.is_active() {
+        return Ok(());
+    }
+
+    let message = match user.status {
+        UserStatus::Banned => ErrorMessage::AccountBanned,
+        UserStatus::Deactivated => ErrorMessage::AccountDeactivated,
+        _ => ErrorMessage::AccountSuspended(user.suspended_until),
+    };
+
+    Err(HttpError::new(message.to_string(), StatusCode::FORBIDDEN))
+}
+
+/// 拒绝通过 API 密钥认证的请求 -- 用于管理密钥、修改密码、两步验证等只允许登录会话操作的路由
+pub async fn require_session(req: Request, next: Next) -> Result<impl IntoResponse, HttpError> {
+    let auth = req
         .extensions()
-        .get::<JWTAuthMiddleware>()
+        .get::<AuthUser>()
         .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNotAuthenticated.to_string()))?;
 
-    if !required_roles.contains(&user.user.role) {
+    if auth.api_key.is_some() {
         return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
+            ErrorMessage::ApiKeyNotAllowed.to_string(),
             StatusCode::FORBIDDEN,
         ));
     }
 
-    // -- 通过 Ok 包装异步执行下一个处理器的结果，将请求传递给路由处理函数继续处理
     Ok(next.run(req).await)
 }
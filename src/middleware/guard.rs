@@ -0,0 +1,356 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use tower::{Layer, Service};
+
+use crate::{
+    error::{ErrorMessage, HttpError},
+    middleware::AuthUser,
+    models::Permission,
+};
+
+/// 访问路由需要满足的条件
+#[derive(Debug, Clone, PartialEq)]
+enum Requirement {
+    /// 拥有任意一个角色
+    AnyRole(Vec<String>),
+    /// 拥有指定权限
+    Permission(Permission),
+}
+
+impl Requirement {
+    /// 检查已认证用户是否满足条件 -- 未认证返回 401，已认证但不满足条件返回 403
+    fn check(&self, auth: Option<&AuthUser>) -> Result<(), HttpError> {
+        let auth = auth.ok_or_else(|| {
+            HttpError::unauthorized(ErrorMessage::UserNotAuthenticated.to_string())
+        })?;
+
+        let allowed = match self {
+            Requirement::AnyRole(roles) => roles.contains(&auth.role),
+            Requirement::Permission(permission) => auth.has_permission(*permission),
+        };
+
+        if !allowed {
+            return Err(HttpError::new(
+                ErrorMessage::PermissionDenied.to_string(),
+                StatusCode::FORBIDDEN,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// 角色守卫层 -- 只允许拥有任意一个指定角色的用户访问，需要放在认证中间件之后
+///
+/// ```ignore
+/// Router::new().route("/users", get(get_users).layer(RequireRole::new([ADMIN_ROLE])))
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequireRole {
+    requirement: Arc<Requirement>,
+}
+
+impl RequireRole {
+    pub fn new(roles: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        RequireRole {
+            requirement: Arc::new(Requirement::AnyRole(
+                roles.into_iter().map(Into::into).collect(),
+            )),
+        }
+    }
+}
+
+/// 权限守卫层 -- 只允许角色拥有指定权限的用户访问，需要放在认证中间件之后
+///
+/// 角色拥有的权限由认证中间件通过 [`PermissionResolver`](crate::rbac::PermissionResolver) 加载。
+///
+/// 可以用在单个路由或整个嵌套路由上：
+///
+/// ```ignore
+/// Router::new()
+///     .route("/me", get(get_me))
+///     .layer(RequirePermission::new(Permission::ManageOwnAccount))
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequirePermission {
+    requirement: Arc<Requirement>,
+}
+
+impl RequirePermission {
+    pub fn new(permission: Permission) -> Self {
+        RequirePermission {
+            requirement: Arc::new(Requirement::Permission(permission)),
+        }
+    }
+}
+
+impl<S> Layer<S> for RequireRole {
+    type Service = GuardService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GuardService {
+            inner,
+            requirement: self.requirement.clone(),
+        }
+    }
+}
+
+impl<S> Layer<S> for RequirePermission {
+    type Service = GuardService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GuardService {
+            inner,
+            requirement: self.requirement.clone(),
+        }
+    }
+}
+
+/// 守卫中间件服务，由 [`RequireRole`] 或 [`RequirePermission`] 创建
+#[derive(Debug, Clone)]
+pub struct GuardService<S> {
+    inner: S,
+    requirement: Arc<Requirement>,
+}
+
+impl<S> Service<Request> for GuardService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        if let Err(error) = self.requirement.check(req.extensions().get::<AuthUser>()) {
+            return Box::pin(async move { Ok(error.into_response()) });
+        }
+
+        // -- 使用已就绪的服务处理本次请求，克隆的服务留给下一次请求
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move { inner.call(req).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::models::{ADMIN_ROLE, USER_ROLE};
+
+    const ALL_PERMISSIONS: [Permission; 6] = [
+        Permission::ManageOwnAccount,
+        Permission::ListUsers,
+        Permission::ManageUsers,
+        Permission::AssignRoles,
+        Permission::ReadRoles,
+        Permission::ManageRoles,
+    ];
+
+    fn auth_user(role: &str, permissions: &[Permission]) -> AuthUser {
+        AuthUser {
+            id: Uuid::new_v4(),
+            role: role.to_string(),
+            permissions: Arc::new(
+                permissions
+                    .iter()
+                    .map(|permission| permission.to_str().to_string())
+                    .collect(),
+            ),
+            session_id: None,
+            token_version: 0,
+            api_key: None,
+            user: None,
+        }
+    }
+
+    fn admin() -> AuthUser {
+        auth_user(ADMIN_ROLE, &ALL_PERMISSIONS)
+    }
+
+    fn user() -> AuthUser {
+        auth_user(USER_ROLE, &[Permission::ManageOwnAccount])
+    }
+
+    async fn status(router: Router, auth: Option<AuthUser>) -> StatusCode {
+        let mut req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        if let Some(auth) = auth {
+            req.extensions_mut().insert(auth);
+        }
+
+        router.oneshot(req).await.unwrap().status()
+    }
+
+    fn guarded<L>(layer: L) -> Router
+    where
+        L: Layer<axum::routing::Route> + Clone + Send + Sync + 'static,
+        L::Service: Service<Request, Response = Response, Error = Infallible>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(layer)
+    }
+
+    #[test]
+    fn permission_names() {
+        let names: Vec<_> = ALL_PERMISSIONS.iter().map(|p| p.to_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "account:manage",
+                "users:read",
+                "users:write",
+                "users:write_role",
+                "roles:read",
+                "roles:write"
+            ]
+        );
+    }
+
+    #[test]
+    fn unauthenticated_is_rejected_with_401() {
+        let requirements = [
+            Requirement::AnyRole(vec![ADMIN_ROLE.to_string(), USER_ROLE.to_string()]),
+            Requirement::Permission(Permission::ManageOwnAccount),
+        ];
+
+        for requirement in requirements {
+            let error = requirement.check(None).unwrap_err();
+            assert_eq!(error.status, StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[test]
+    fn missing_role_is_rejected_with_403() {
+        let requirement = Requirement::AnyRole(vec![ADMIN_ROLE.to_string()]);
+
+        let error = requirement.check(Some(&user())).unwrap_err();
+        assert_eq!(error.status, StatusCode::FORBIDDEN);
+        assert!(requirement.check(Some(&admin())).is_ok());
+    }
+
+    #[test]
+    fn empty_role_list_rejects_everyone() {
+        let requirement = Requirement::AnyRole(Vec::new());
+
+        for auth in [admin(), user()] {
+            let error = requirement.check(Some(&auth)).unwrap_err();
+            assert_eq!(error.status, StatusCode::FORBIDDEN);
+        }
+    }
+
+    #[test]
+    fn permission_comes_from_permission_set_not_role_name() {
+        let requirement = Requirement::Permission(Permission::ListUsers);
+
+        // -- 自定义角色只要拥有权限即可访问，内置角色名本身不代表任何权限
+        let auditor = auth_user("auditor", &[Permission::ListUsers]);
+        assert!(requirement.check(Some(&auditor)).is_ok());
+
+        let stripped_admin = auth_user(ADMIN_ROLE, &[]);
+        let error = requirement.check(Some(&stripped_admin)).unwrap_err();
+        assert_eq!(error.status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn require_role_layer() {
+        let cases = [
+            (vec![ADMIN_ROLE], None, StatusCode::UNAUTHORIZED),
+            (vec![ADMIN_ROLE], Some(user()), StatusCode::FORBIDDEN),
+            (vec![ADMIN_ROLE], Some(admin()), StatusCode::OK),
+            (vec![ADMIN_ROLE, USER_ROLE], Some(user()), StatusCode::OK),
+            (vec![ADMIN_ROLE, USER_ROLE], None, StatusCode::UNAUTHORIZED),
+        ];
+
+        for (roles, auth, expected) in cases {
+            let role = auth.as_ref().map(|auth| auth.role.clone());
+            let router = guarded(RequireRole::new(roles.clone()));
+            assert_eq!(
+                status(router, auth).await,
+                expected,
+                "{:?} {:?}",
+                roles,
+                role
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn require_permission_layer() {
+        for permission in ALL_PERMISSIONS {
+            let router = guarded(RequirePermission::new(permission));
+            assert_eq!(status(router, None).await, StatusCode::UNAUTHORIZED);
+
+            for auth in [admin(), user()] {
+                let expected = if auth.has_permission(permission) {
+                    StatusCode::OK
+                } else {
+                    StatusCode::FORBIDDEN
+                };
+                let role = auth.role.clone();
+                let router = guarded(RequirePermission::new(permission));
+                assert_eq!(
+                    status(router, Some(auth)).await,
+                    expected,
+                    "{:?} {:?}",
+                    permission,
+                    role
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn guards_nested_router() {
+        let router = Router::new().nest(
+            "/admin",
+            Router::new()
+                .route("/", get(|| async { "ok" }))
+                .layer(RequireRole::new([ADMIN_ROLE])),
+        );
+
+        for (auth, expected) in [
+            (None, StatusCode::UNAUTHORIZED),
+            (Some(user()), StatusCode::FORBIDDEN),
+            (Some(admin()), StatusCode::OK),
+        ] {
+            let role = auth.as_ref().map(|auth| auth.role.clone());
+            let mut req = Request::builder()
+                .uri("/admin")
+                .body(Body::empty())
+                .unwrap();
+            if let Some(auth) = auth {
+                req.extensions_mut().insert(auth);
+            }
+
+            let response = router.clone().oneshot(req).await.unwrap();
+            assert_eq!(response.status(), expected, "{:?}", role);
+        }
+    }
+}
@@ -1,18 +1,114 @@
 use chrono::prelude::*;
+use chrono::Duration;
 use serde::{Deserialize, Serialize};
 
+/// 内置的管理员角色 -- 拥有全部权限，权限不能修改
+pub const ADMIN_ROLE: &str = "admin";
+/// 内置的普通用户角色 -- 新注册用户的默认角色
+pub const USER_ROLE: &str = "user";
+
+/// 接口权限 -- 路由通过 `RequirePermission` 声明所需权限，角色拥有的权限保存在 `role_permissions` 表中
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// 查看和管理自己的账户（个人信息、密码、两步验证、通行密钥、API 密钥）
+    ManageOwnAccount,
+    /// 查看用户列表
+    ListUsers,
+    /// 管理其他用户（解除锁定、强制修改密码）
+    ManageUsers,
+    /// 修改用户角色
+    AssignRoles,
+    /// 查看角色和权限列表
+    ReadRoles,
+    /// 创建、修改和删除角色
+    ManageRoles,
+}
+
+impl Permission {
+    /// 权限在 `permissions` 表中的名称
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Permission::ManageOwnAccount => "account:manage",
+            Permission::ListUsers => "users:read",
+            Permission::ManageUsers => "users:write",
+            Permission::AssignRoles => "users:write_role",
+            Permission::ReadRoles => "roles:read",
+            Permission::ManageRoles => "roles:write",
+        }
+    }
+}
+
+/// 角色 -- `is_system` 为内置角色，不能删除
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct Role {
+    pub name: String,
+    pub description: String,
+    #[serde(rename = "isSystem")]
+    pub is_system: bool,
+    pub permissions: Vec<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// 权限定义 -- 权限由迁移脚本写入，接口只能查看
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct PermissionInfo {
+    pub name: String,
+    pub description: String,
+}
+
+/// 账户状态 -- 只有正常状态的账户可以登录和调用接口
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, sqlx::Type, PartialEq)]
-#[sqlx(type_name = "user_role", rename_all = "lowercase")]
-pub enum UserRole {
-    Admin,
-    User
+#[sqlx(type_name = "user_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum UserStatus {
+    /// 正常
+    Active,
+    /// 暂停 -- 可以设置截止时间，到期后自动恢复
+    Suspended,
+    /// 封禁 -- 只能由管理员解除
+    Banned,
+    /// 停用
+    Deactivated,
 }
 
-impl UserRole {
+impl UserStatus {
     pub fn to_str(self) -> &'static str {
         match self {
-            UserRole::Admin => "admin",
-            UserRole::User => "user",
+            UserStatus::Active => "active",
+            UserStatus::Suspended => "suspended",
+            UserStatus::Banned => "banned",
+            UserStatus::Deactivated => "deactivated",
+        }
+    }
+}
+
+/// 邮件令牌用途 -- 每种用途的令牌只能用于对应的操作
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "user_token_purpose", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum UserTokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+    ChangeEmail,
+    MagicLogin,
+    Invite,
+    UnlockAccount,
+}
+
+impl UserTokenPurpose {
+    /// 该用途令牌的有效期
+    pub fn ttl(self) -> Duration {
+        match self {
+            UserTokenPurpose::VerifyEmail => Duration::minutes(30),
+            UserTokenPurpose::ResetPassword => Duration::minutes(30),
+            UserTokenPurpose::ChangeEmail => Duration::hours(1),
+            UserTokenPurpose::MagicLogin => Duration::minutes(15),
+            UserTokenPurpose::Invite => Duration::days(7),
+            UserTokenPurpose::UnlockAccount => Duration::hours(1),
         }
     }
 }
@@ -23,13 +119,264 @@ pub struct User {
     pub name: String,
     pub email: String,
     pub password: String,
-    pub role: UserRole,
+    pub role: String,
     pub verified: bool,
-    pub verification_token: Option<String>,
-    pub token_expires_at: Option<DateTime<Utc>>,
+    pub token_version: i32,
+    pub mfa_enabled: bool,
+    pub totp_secret: Option<String>,
+    pub totp_pending_secret: Option<String>,
+    pub totp_last_used_step: Option<i64>,
+    pub failed_login_attempts: i32,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub password_changed_at: DateTime<Utc>,
+    pub must_change_password: bool,
+    pub status: UserStatus,
+    /// 管理员修改账户状态时填写的原因
+    pub status_reason: Option<String>,
+    /// 暂停的截止时间 -- 为空表示无限期暂停，到期后由后台任务恢复为正常
+    pub suspended_until: Option<DateTime<Utc>>,
+    /// 用户申请删除账户的时间 -- 宽限期内登录会恢复账户，宽限期过后由后台任务彻底删除
+    pub deleted_at: Option<DateTime<Utc>>,
     #[serde(rename = "createdAt")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<DateTime<Utc>>,
 
 }
+
+impl User {
+    /// 账户是否处于登录锁定期
+    pub fn is_locked(&self) -> bool {
+        self.locked_until
+            .is_some_and(|locked_until| locked_until > Utc::now())
+    }
+
+    /// 账户当前是否可以使用 -- 暂停已到期的账户视为正常，不必等待后台任务恢复
+    pub fn is_active(&self) -> bool {
+        match self.status {
+            UserStatus::Active => true,
+            UserStatus::Suspended => self
+                .suspended_until
+                .is_some_and(|suspended_until| suspended_until <= Utc::now()),
+            UserStatus::Banned | UserStatus::Deactivated => false,
+        }
+    }
+
+    /// 账户是否已申请删除、正在宽限期内
+    pub fn is_pending_deletion(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// 删除申请是否已超过 `grace_days` 天的宽限期 -- 后台任务尚未执行时账户仍在数据库中，但视为已删除
+    pub fn deletion_expired(&self, grace_days: i64) -> bool {
+        self.deleted_at
+            .is_some_and(|deleted_at| deleted_at + chrono::Duration::days(grace_days) <= Utc::now())
+    }
+
+    /// 登录后是否必须先修改密码 -- 管理员要求修改，或密码已超过 `max_age_days` 天未修改（0 表示不限制）
+    pub fn password_change_required(&self, max_age_days: i64) -> bool {
+        self.must_change_password
+            || (max_age_days > 0
+                && self.password_changed_at + chrono::Duration::days(max_age_days) < Utc::now())
+    }
+}
+
+/// 刷新令牌会话 -- 每次轮换都会在同一 family 下生成一条新记录
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct Session {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub family_id: uuid::Uuid,
+    pub refresh_token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub rotated_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// 两步验证恢复码 -- 只保存 argon2 哈希，每个恢复码只能使用一次
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct RecoveryCode {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub code_hash: String,
+    pub used_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// WebAuthn 仪式类型 -- 区分挑战的用途，防止挑战被挪作他用
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "webauthn_ceremony", rename_all = "lowercase")]
+pub enum WebauthnCeremony {
+    Registration,
+    Authentication,
+    Mfa,
+}
+
+/// WebAuthn 凭证 -- `passkey` 保存 webauthn-rs 序列化后的公钥与计数器
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct WebauthnCredential {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub credential_id: Vec<u8>,
+    pub name: String,
+    pub passkey: serde_json::Value,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// WebAuthn 挑战 -- 保存注册或认证仪式的服务端状态，只能使用一次
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct WebauthnChallenge {
+    pub id: uuid::Uuid,
+    pub user_id: Option<uuid::Uuid>,
+    pub ceremony: WebauthnCeremony,
+    pub state: serde_json::Value,
+    pub expires_at: DateTime<Utc>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// 第三方身份 -- 将 OIDC 提供方的用户标识（`sub`）关联到本地用户
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct UserIdentity {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    #[serde(rename = "lastLoginAt")]
+    pub last_login_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// OAuth 授权状态 -- 保存授权码流程中的 PKCE 校验码与 nonce，只能使用一次
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct OAuthState {
+    pub state_hash: String,
+    pub provider: String,
+    pub pkce_verifier: String,
+    pub nonce: String,
+    pub expires_at: DateTime<Utc>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyScope {
+    Read,
+    Write,
+}
+
+impl ApiKeyScope {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            ApiKeyScope::Read => "read",
+            ApiKeyScope::Write => "write",
+        }
+    }
+}
+
+/// API 密钥 -- 供脚本和服务调用接口，只保存密钥的 SHA-256 哈希
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct ApiKey {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub name: String,
+    /// 密钥开头的若干字符，用于在列表中辨认密钥
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(rename = "revokedAt")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.iter().any(|s| s == scope.to_str())
+    }
+}
+
+/// 审计事件类型 -- 记录管理员对其他账户的操作，以及账户删除等影响账户存续的操作
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    UserUpdated,
+    StatusChanged,
+    UserDeleted,
+    UserUnlocked,
+    PasswordChangeForced,
+    PasswordChangeCleared,
+    AccountDeletionRequested,
+    AccountRestored,
+    AccountPurged,
+    DataExportRequested,
+}
+
+impl AuditAction {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            AuditAction::UserUpdated => "user_updated",
+            AuditAction::StatusChanged => "status_changed",
+            AuditAction::UserDeleted => "user_deleted",
+            AuditAction::UserUnlocked => "user_unlocked",
+            AuditAction::PasswordChangeForced => "password_change_forced",
+            AuditAction::PasswordChangeCleared => "password_change_cleared",
+            AuditAction::AccountDeletionRequested => "account_deletion_requested",
+            AuditAction::AccountRestored => "account_restored",
+            AuditAction::AccountPurged => "account_purged",
+            AuditAction::DataExportRequested => "data_export_requested",
+        }
+    }
+}
+
+/// 审计事件 -- `actor_id` 为操作者（后台任务自动执行的操作为空），`target_user_id` 为被操作的用户，用户删除后记录仍然保留
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct AuditEvent {
+    pub id: uuid::Uuid,
+    #[serde(rename = "actorId")]
+    pub actor_id: Option<uuid::Uuid>,
+    #[serde(rename = "targetUserId")]
+    pub target_user_id: Option<uuid::Uuid>,
+    pub action: String,
+    pub details: serde_json::Value,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// 登录尝试记录 -- 邮箱未注册时 `user_id` 为空
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct LoginAttempt {
+    pub id: uuid::Uuid,
+    #[serde(rename = "userId")]
+    pub user_id: Option<uuid::Uuid>,
+    pub email: String,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: String,
+    pub success: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// 个人数据导出 -- 后台生成完成后写入 `data` 和 `completed_at`，通过邮件中的下载链接获取，过期后删除
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct DataExport {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub token_hash: String,
+    pub data: Option<serde_json::Value>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: Option<DateTime<Utc>>,
+}
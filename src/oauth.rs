@@ -0,0 +1,83 @@
+pub mod oidc;
+
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use async_trait::async_trait;
+
+use crate::config::Config;
+
+/// 身份提供方返回的错误
+pub type OAuthError = Box<dyn std::error::Error + Send + Sync>;
+
+/// 身份提供方认证后返回的外部身份
+#[derive(Debug, Clone)]
+pub struct ExternalIdentity {
+    /// 提供方内的用户唯一标识（`sub`）
+    pub subject: String,
+    pub email: Option<String>,
+    /// 提供方是否已验证该邮箱，只有已验证的邮箱才会用于关联本地账户
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+/// 第三方身份提供方 -- 封装授权码 + PKCE 流程，新增提供方只需实现该特征
+#[async_trait]
+pub trait IdentityProvider: Debug + Send + Sync {
+    /// 构建授权地址 -- 用户浏览器将被重定向到该地址
+    ///
+    /// # 参数
+    /// - `state` -- 防 CSRF 的随机值，回调时原样返回
+    /// - `nonce` -- 绑定到 ID 令牌的随机值，防止令牌重放
+    /// - `pkce_verifier` -- PKCE 校验码，授权地址中只携带其 SHA-256 摘要
+    async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        pkce_verifier: &str,
+    ) -> Result<String, OAuthError>;
+
+    /// 使用授权码换取令牌并校验，返回外部身份
+    async fn exchange_code(
+        &self,
+        code: &str,
+        nonce: &str,
+        pkce_verifier: &str,
+    ) -> Result<ExternalIdentity, OAuthError>;
+}
+
+/// 已配置的身份提供方，按名称索引
+#[derive(Debug, Clone, Default)]
+pub struct IdentityProviders {
+    providers: HashMap<String, Arc<dyn IdentityProvider>>,
+}
+
+impl IdentityProviders {
+    /// 根据配置创建全部 OIDC 提供方
+    pub fn from_config(config: &Config) -> Result<Self, OAuthError> {
+        let mut providers: HashMap<String, Arc<dyn IdentityProvider>> = HashMap::new();
+
+        for provider_config in &config.oidc_providers {
+            let redirect_url = format!(
+                "{}/{}/callback",
+                config.oauth_redirect_base_url.trim_end_matches('/'),
+                provider_config.name
+            );
+            let provider = oidc::OidcProvider::new(provider_config.clone(), redirect_url)?;
+            providers.insert(provider_config.name.clone(), Arc::new(provider));
+        }
+
+        Ok(IdentityProviders { providers })
+    }
+
+    /// 已配置的身份提供方名称，按字母排序
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.providers.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// 根据名称获取身份提供方
+    pub fn get(&self, name: &str) -> Option<Arc<dyn IdentityProvider>> {
+        self.providers.get(name).cloned()
+    }
+}
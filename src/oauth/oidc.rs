@@ -0,0 +1,394 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use openidconnect::{
+    core::{CoreAuthenticationFlow, CoreClient, CoreProviderMetadata},
+    reqwest, AuthorizationCode, ClientId, ClientSecret, CsrfToken, EndpointMaybeSet,
+    EndpointNotSet, EndpointSet, IssuerUrl, Nonce, PkceCodeChallenge, PkceCodeVerifier,
+    RedirectUrl, Scope,
+};
+use tokio::sync::RwLock;
+
+use super::{ExternalIdentity, IdentityProvider, OAuthError};
+use crate::config::OidcProviderConfig;
+
+/// 发现文档缓存时间 -- 过期后重新获取，以便及时拿到轮换后的签名公钥
+const METADATA_TTL: Duration = Duration::from_secs(3600);
+
+type DiscoveredClient = CoreClient<
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+
+/// 标准 OpenID Connect 提供方 -- 通过 `{issuer}/.well-known/openid-configuration` 自动发现端点
+#[derive(Debug)]
+pub struct OidcProvider {
+    config: OidcProviderConfig,
+    issuer_url: IssuerUrl,
+    redirect_url: RedirectUrl,
+    http_client: reqwest::Client,
+    metadata: RwLock<Option<(Instant, CoreProviderMetadata)>>,
+}
+
+impl OidcProvider {
+    pub fn new(config: OidcProviderConfig, redirect_url: String) -> Result<Self, OAuthError> {
+        let issuer_url = IssuerUrl::new(config.issuer_url.clone())?;
+        let redirect_url = RedirectUrl::new(redirect_url)?;
+
+        // -- 禁止跟随重定向，防止 SSRF
+        let http_client = reqwest::ClientBuilder::new()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(Duration::from_secs(10))
+            .build()?;
+
+        Ok(OidcProvider {
+            config,
+            issuer_url,
+            redirect_url,
+            http_client,
+            metadata: RwLock::new(None),
+        })
+    }
+
+    /// 获取发现文档 -- 首次使用时才请求提供方，避免提供方不可用时服务无法启动
+    async fn provider_metadata(&self) -> Result<CoreProviderMetadata, OAuthError> {
+        if let Some((fetched_at, metadata)) = self.metadata.read().await.as_ref() {
+            if fetched_at.elapsed() < METADATA_TTL {
+                return Ok(metadata.clone());
+            }
+        }
+
+        let metadata =
+            CoreProviderMetadata::discover_async(self.issuer_url.clone(), &self.http_client)
+                .await?;
+        *self.metadata.write().await = Some((Instant::now(), metadata.clone()));
+
+        Ok(metadata)
+    }
+
+    async fn client(&self) -> Result<DiscoveredClient, OAuthError> {
+        let metadata = self.provider_metadata().await?;
+
+        let client = CoreClient::from_provider_metadata(
+            metadata,
+            ClientId::new(self.config.client_id.clone()),
+            self.config.client_secret.clone().map(ClientSecret::new),
+        )
+        .set_redirect_uri(self.redirect_url.clone());
+
+        Ok(client)
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for OidcProvider {
+    async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        pkce_verifier: &str,
+    ) -> Result<String, OAuthError> {
+        let client = self.client().await?;
+        let pkce_challenge = PkceCodeChallenge::from_code_verifier_sha256(&PkceCodeVerifier::new(
+            pkce_verifier.to_string(),
+        ));
+
+        let state = state.to_string();
+        let nonce = nonce.to_string();
+        let (url, _, _) = client
+            .authorize_url(
+                CoreAuthenticationFlow::AuthorizationCode,
+                move || CsrfToken::new(state),
+                move || Nonce::new(nonce),
+            )
+            .add_scopes(
+                self.config
+                    .scopes
+                    .iter()
+                    .filter(|scope| scope.as_str() != "openid")
+                    .map(|scope| Scope::new(scope.clone())),
+            )
+            .set_pkce_challenge(pkce_challenge)
+            .url();
+
+        Ok(url.to_string())
+    }
+
+    async fn exchange_code(
+        &self,
+        code: &str,
+        nonce: &str,
+        pkce_verifier: &str,
+    ) -> Result<ExternalIdentity, OAuthError> {
+        let client = self.client().await?;
+
+        let token_response = client
+            .exchange_code(AuthorizationCode::new(code.to_string()))?
+            .set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier.to_string()))
+            .request_async(&self.http_client)
+            .await?;
+
+        // -- 校验 ID 令牌的签名、签发方、受众、有效期和 nonce
+        let id_token = token_response
+            .extra_fields()
+            .id_token()
+            .ok_or("Provider did not return an ID token")?;
+        let claims =
+            id_token.claims(&client.id_token_verifier(), &Nonce::new(nonce.to_string()))?;
+
+        let name = claims
+            .name()
+            .and_then(|name| name.get(None))
+            .map(|name| name.to_string())
+            .or_else(|| claims.preferred_username().map(|name| name.to_string()));
+
+        Ok(ExternalIdentity {
+            subject: claims.subject().to_string(),
+            email: claims.email().map(|email| email.to_string()),
+            email_verified: claims.email_verified().unwrap_or(false),
+            name,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex, OnceLock},
+    };
+
+    use argon2::password_hash::rand_core::OsRng;
+    use axum::{extract::State, http::StatusCode, routing::get, Form, Json, Router};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+    use openidconnect::url::Url;
+    use rsa::{
+        pkcs1::EncodeRsaPrivateKey, pkcs8::LineEnding, traits::PublicKeyParts, RsaPrivateKey,
+    };
+    use serde_json::{json, Value};
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::utils::token::generate_opaque_token;
+
+    const CLIENT_ID: &str = "test-client";
+
+    /// 测试共用的签名密钥和对应的 JWKS -- 调试构建下生成 RSA 密钥较慢，只生成一次
+    fn signing_key() -> &'static (EncodingKey, Value) {
+        static KEY: OnceLock<(EncodingKey, Value)> = OnceLock::new();
+        KEY.get_or_init(|| {
+            let private_key = RsaPrivateKey::new(&mut OsRng, 2048).unwrap();
+            let pem = private_key.to_pkcs1_pem(LineEnding::LF).unwrap();
+            let jwks = json!({
+                "keys": [{
+                    "kty": "RSA",
+                    "use": "sig",
+                    "alg": "RS256",
+                    "kid": "test",
+                    "n": URL_SAFE_NO_PAD.encode(private_key.n().to_bytes_be()),
+                    "e": URL_SAFE_NO_PAD.encode(private_key.e().to_bytes_be()),
+                }]
+            });
+            (EncodingKey::from_rsa_pem(pem.as_bytes()).unwrap(), jwks)
+        })
+    }
+
+    /// 本地模拟的身份提供方 -- 提供发现文档、JWKS 和令牌端点，ID 令牌由测试生成的 RSA 密钥签名
+    struct MockIdp {
+        issuer: String,
+        encoding_key: EncodingKey,
+        jwks: Value,
+        /// 已授权的授权码 -> (写入 ID 令牌的 nonce, PKCE 摘要)
+        grants: Mutex<HashMap<String, (String, String)>>,
+    }
+
+    impl MockIdp {
+        async fn start() -> Arc<MockIdp> {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let issuer = format!("http://{}", listener.local_addr().unwrap());
+            let (encoding_key, jwks) = signing_key().clone();
+
+            let idp = Arc::new(MockIdp {
+                issuer,
+                encoding_key,
+                jwks,
+                grants: Mutex::new(HashMap::new()),
+            });
+
+            let app = Router::new()
+                .route("/.well-known/openid-configuration", get(discovery))
+                .route(
+                    "/jwks",
+                    get(|State(idp): State<Arc<MockIdp>>| async move { Json(idp.jwks.clone()) }),
+                )
+                .route("/token", axum::routing::post(token))
+                .with_state(idp.clone());
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+            idp
+        }
+
+        fn provider(&self) -> OidcProvider {
+            let config = OidcProviderConfig {
+                name: "mock".to_string(),
+                issuer_url: self.issuer.clone(),
+                client_id: CLIENT_ID.to_string(),
+                client_secret: Some("secret".to_string()),
+                scopes: vec!["openid".to_string(), "email".to_string()],
+            };
+            OidcProvider::new(
+                config,
+                "http://localhost/api/auth/oauth/mock/callback".to_string(),
+            )
+            .unwrap()
+        }
+
+        /// 模拟用户在提供方同意授权，返回授权码
+        fn approve(&self, nonce: &str, code_challenge: &str) -> String {
+            let code = generate_opaque_token();
+            self.grants.lock().unwrap().insert(
+                code.clone(),
+                (nonce.to_string(), code_challenge.to_string()),
+            );
+            code
+        }
+    }
+
+    async fn discovery(State(idp): State<Arc<MockIdp>>) -> Json<Value> {
+        Json(json!({
+            "issuer": idp.issuer,
+            "authorization_endpoint": format!("{}/authorize", idp.issuer),
+            "token_endpoint": format!("{}/token", idp.issuer),
+            "jwks_uri": format!("{}/jwks", idp.issuer),
+            "response_types_supported": ["code"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["RS256"],
+        }))
+    }
+
+    async fn token(
+        State(idp): State<Arc<MockIdp>>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+        let invalid_grant = || {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "invalid_grant"})),
+            )
+        };
+
+        let (nonce, code_challenge) = form
+            .get("code")
+            .and_then(|code| idp.grants.lock().unwrap().remove(code))
+            .ok_or_else(invalid_grant)?;
+
+        let verifier = form.get("code_verifier").ok_or_else(invalid_grant)?;
+        if URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) != code_challenge {
+            return Err(invalid_grant());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let claims = json!({
+            "iss": idp.issuer,
+            "sub": "mock-subject",
+            "aud": CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": nonce,
+            "email": "mock@example.com",
+            "email_verified": true,
+            "name": "Mock User",
+        });
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("test".to_string());
+        let id_token = jsonwebtoken::encode(&header, &claims, &idp.encoding_key).unwrap();
+
+        Ok(Json(json!({
+            "access_token": "mock-access-token",
+            "token_type": "Bearer",
+            "expires_in": 300,
+            "id_token": id_token,
+        })))
+    }
+
+    /// 发起授权并返回授权地址中的查询参数
+    async fn authorize(
+        provider: &OidcProvider,
+        nonce: &str,
+        pkce_verifier: &str,
+    ) -> HashMap<String, String> {
+        let url = provider
+            .authorization_url("state-value", nonce, pkce_verifier)
+            .await
+            .unwrap();
+        Url::parse(&url)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn mock_idp_login_round_trip() {
+        let idp = MockIdp::start().await;
+        let provider = idp.provider();
+
+        let (nonce, pkce_verifier) = (generate_opaque_token(), generate_opaque_token());
+
+        let params = authorize(&provider, &nonce, &pkce_verifier).await;
+        assert_eq!(params["state"], "state-value");
+        assert_eq!(params["nonce"], nonce);
+        assert_eq!(params["client_id"], CLIENT_ID);
+        assert_eq!(params["code_challenge_method"], "S256");
+
+        let code = idp.approve(&params["nonce"], &params["code_challenge"]);
+        let identity = provider
+            .exchange_code(&code, &nonce, &pkce_verifier)
+            .await
+            .unwrap();
+
+        assert_eq!(identity.subject, "mock-subject");
+        assert_eq!(identity.email.as_deref(), Some("mock@example.com"));
+        assert!(identity.email_verified);
+        assert_eq!(identity.name.as_deref(), Some("Mock User"));
+    }
+
+    #[tokio::test]
+    async fn rejects_id_token_with_mismatched_nonce() {
+        let idp = MockIdp::start().await;
+        let provider = idp.provider();
+
+        let (nonce, pkce_verifier) = (generate_opaque_token(), generate_opaque_token());
+
+        // -- 授权码来自另一次授权，ID 令牌中的 nonce 与本次保存的不一致
+        let params = authorize(&provider, &nonce, &pkce_verifier).await;
+        let code = idp.approve(&generate_opaque_token(), &params["code_challenge"]);
+
+        let error = provider
+            .exchange_code(&code, &nonce, &pkce_verifier)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("Invalid nonce"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_pkce_verifier() {
+        let idp = MockIdp::start().await;
+        let provider = idp.provider();
+
+        let (nonce, pkce_verifier) = (generate_opaque_token(), generate_opaque_token());
+
+        let params = authorize(&provider, &nonce, &pkce_verifier).await;
+        let code = idp.approve(&params["nonce"], &params["code_challenge"]);
+
+        assert!(provider
+            .exchange_code(&code, &nonce, &generate_opaque_token())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,286 @@
+pub mod memory;
+
+use std::{
+    convert::Infallible,
+    fmt::Debug,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, OriginalUri, Request},
+    http::Method,
+    response::{IntoResponse, Response},
+};
+use tower::{Layer, Service};
+
+use crate::{
+    error::{ErrorMessage, HttpError},
+    middleware::AuthUser,
+};
+
+/// 限流存储返回的错误
+pub type RateLimitError = Box<dyn std::error::Error + Send + Sync>;
+
+/// 读取请求体中邮箱时允许的最大请求体大小
+const MAX_BUFFERED_BODY_BYTES: usize = 64 * 1024;
+
+/// 限流键的来源
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitKey {
+    /// 客户端 IP
+    Ip,
+    /// 请求体 JSON 中的 `email` 字段，缺少该字段时不限流
+    Email,
+    /// 已认证用户的ID -- 需要放在认证中间件之后，未认证时退化为按 IP 限流
+    UserId,
+}
+
+impl RateLimitKey {
+    fn to_str(self) -> &'static str {
+        match self {
+            RateLimitKey::Ip => "ip",
+            RateLimitKey::Email => "email",
+            RateLimitKey::UserId => "user",
+        }
+    }
+}
+
+/// 令牌桶限流规则 -- 桶容量为 `capacity`，每经过 `period` 补满一次
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitRule {
+    pub key: RateLimitKey,
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl RateLimitRule {
+    pub fn new(key: RateLimitKey, capacity: u32, period: Duration) -> Self {
+        RateLimitRule {
+            key,
+            capacity: capacity.max(1),
+            period,
+        }
+    }
+
+    pub fn per_ip(capacity: u32, period: Duration) -> Self {
+        Self::new(RateLimitKey::Ip, capacity, period)
+    }
+
+    pub fn per_email(capacity: u32, period: Duration) -> Self {
+        Self::new(RateLimitKey::Email, capacity, period)
+    }
+
+    pub fn per_user(capacity: u32, period: Duration) -> Self {
+        Self::new(RateLimitKey::UserId, capacity, period)
+    }
+}
+
+/// 限流判定结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitDecision {
+    Allowed,
+    /// 超出限制，`retry_after` 后桶中才会有可用令牌
+    Limited {
+        retry_after: Duration,
+    },
+}
+
+/// 限流存储 -- 默认使用进程内存，多实例部署时可实现该特征改用 Redis 等共享存储
+#[async_trait]
+pub trait RateLimitStore: Debug + Send + Sync {
+    /// 从 `key` 对应的令牌桶中取出一个令牌
+    async fn acquire(
+        &self,
+        key: &str,
+        rule: &RateLimitRule,
+    ) -> Result<RateLimitDecision, RateLimitError>;
+}
+
+/// 单个路由上的限流规则
+#[derive(Debug, Clone)]
+struct RouteLimit {
+    method: Method,
+    path: String,
+    rule: RateLimitRule,
+}
+
+/// 限流中间件层 -- 按 `方法 + 完整路径` 为路由配置一条或多条限流规则
+///
+/// 同一路由的所有规则都会扣减令牌，任意一条超限即返回 429 和 `Retry-After` 响应头。
+/// 存储出错时记录日志并放行请求，避免限流存储故障导致接口不可用。
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    store: Arc<dyn RateLimitStore>,
+    limits: Arc<Vec<RouteLimit>>,
+}
+
+impl RateLimitLayer {
+    pub fn new(store: Arc<dyn RateLimitStore>) -> Self {
+        RateLimitLayer {
+            store,
+            limits: Arc::new(Vec::new()),
+        }
+    }
+
+    /// 为路由添加限流规则
+    ///
+    /// # 参数
+    /// - `method` -- 请求方法
+    /// - `path` -- 包含 `/api` 前缀的完整路径，例如 `/api/auth/register`
+    /// - `rules` -- 限流规则，请求需要同时满足全部规则
+    pub fn limit(
+        mut self,
+        method: Method,
+        path: &str,
+        rules: impl IntoIterator<Item = RateLimitRule>,
+    ) -> Self {
+        let limits = Arc::make_mut(&mut self.limits);
+        for rule in rules {
+            limits.push(RouteLimit {
+                method: method.clone(),
+                path: path.to_string(),
+                rule,
+            });
+        }
+        self
+    }
+
+    /// 检查请求是否超出限制 -- 需要按邮箱限流时会读取并还原请求体
+    async fn check(&self, req: Request) -> Result<Request, HttpError> {
+        let path = req
+            .extensions()
+            .get::<OriginalUri>()
+            .map(|uri| uri.0.path().to_string())
+            .unwrap_or_else(|| req.uri().path().to_string());
+
+        let limits: Vec<&RouteLimit> = self
+            .limits
+            .iter()
+            .filter(|limit| limit.method == req.method() && limit.path == path)
+            .collect();
+
+        if limits.is_empty() {
+            return Ok(req);
+        }
+
+        let (req, email) = if limits
+            .iter()
+            .any(|limit| limit.rule.key == RateLimitKey::Email)
+        {
+            read_email(req).await?
+        } else {
+            (req, None)
+        };
+
+        let ip = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+        let user_id = req
+            .extensions()
+            .get::<AuthUser>()
+            .map(|auth| auth.id.to_string());
+
+        for limit in limits {
+            let value = match limit.rule.key {
+                RateLimitKey::Ip => ip.clone(),
+                RateLimitKey::Email => email.clone(),
+                RateLimitKey::UserId => user_id.clone().or_else(|| ip.clone()),
+            };
+            let Some(value) = value else {
+                continue;
+            };
+
+            let key = format!(
+                "{} {}:{}:{}",
+                limit.method,
+                limit.path,
+                limit.rule.key.to_str(),
+                value
+            );
+
+            match self.store.acquire(&key, &limit.rule).await {
+                Ok(RateLimitDecision::Allowed) => {}
+                Ok(RateLimitDecision::Limited { retry_after }) => {
+                    tracing::warn!("请求超出限流: {}", key);
+                    return Err(HttpError::too_many_requests(
+                        ErrorMessage::TooManyRequests.to_string(),
+                        retry_after,
+                    ));
+                }
+                Err(e) => tracing::error!("限流存储出错，放行请求: {}", e),
+            }
+        }
+
+        Ok(req)
+    }
+}
+
+/// 读取请求体中的邮箱（小写），并用读取到的字节重建请求
+async fn read_email(req: Request) -> Result<(Request, Option<String>), HttpError> {
+    let (parts, body) = req.into_parts();
+    let bytes = to_bytes(body, MAX_BUFFERED_BODY_BYTES)
+        .await
+        .map_err(|_| HttpError::bad_request("Request body is too large"))?;
+
+    let email = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|value| value.get("email")?.as_str().map(str::to_string))
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty());
+
+    Ok((Request::from_parts(parts, Body::from(bytes)), email))
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// 限流中间件服务，由 [`RateLimitLayer`] 创建
+#[derive(Debug, Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    layer: RateLimitLayer,
+}
+
+impl<S> Service<Request> for RateLimitService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        // -- 使用已就绪的服务处理本次请求，克隆的服务留给下一次请求
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            match layer.check(req).await {
+                Ok(req) => inner.call(req).await,
+                Err(error) => Ok(error.into_response()),
+            }
+        })
+    }
+}
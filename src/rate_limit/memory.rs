@@ -0,0 +1,69 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use super::{RateLimitDecision, RateLimitError, RateLimitRule, RateLimitStore};
+
+/// 令牌桶数量超过该值时清理已补满的令牌桶
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    period: Duration,
+}
+
+/// 进程内存限流存储 -- 只在单个实例内生效，服务重启后计数清零
+#[derive(Debug, Default)]
+pub struct InMemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl InMemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn acquire(
+        &self,
+        key: &str,
+        rule: &RateLimitRule,
+    ) -> Result<RateLimitDecision, RateLimitError> {
+        let capacity = f64::from(rule.capacity);
+        // -- 每秒补充的令牌数
+        let refill_rate = capacity / rule.period.as_secs_f64().max(f64::EPSILON);
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().map_err(|e| e.to_string())?;
+
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated_at) < bucket.period);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+            period: rule.period,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_rate).min(capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(RateLimitDecision::Allowed);
+        }
+
+        let retry_after = Duration::from_secs_f64((1.0 - bucket.tokens) / refill_rate);
+        Ok(RateLimitDecision::Limited { retry_after })
+    }
+}
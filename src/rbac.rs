@@ -0,0 +1,79 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::db::{DBClient, RoleExt};
+
+/// 角色拥有的权限名称
+pub type PermissionSet = Arc<HashSet<String>>;
+
+#[derive(Debug)]
+struct CachedPermissions {
+    permissions: PermissionSet,
+    loaded_at: Instant,
+}
+
+/// 角色权限解析器 -- 认证中间件通过它查询角色拥有的权限
+///
+/// 查询结果按角色缓存 `ttl` 时间。通过角色接口修改角色后会立即清除本实例的缓存，
+/// 多实例部署时其他实例最多在 `ttl` 之后生效。
+#[derive(Debug)]
+pub struct PermissionResolver {
+    db_client: DBClient,
+    ttl: Duration,
+    cache: RwLock<HashMap<String, CachedPermissions>>,
+}
+
+impl PermissionResolver {
+    pub fn new(db_client: DBClient, ttl: Duration) -> Self {
+        PermissionResolver {
+            db_client,
+            ttl,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 获取角色拥有的权限 -- 缓存过期或不存在时查询数据库，角色不存在时返回空集合
+    pub async fn permissions(&self, role: &str) -> Result<PermissionSet, sqlx::Error> {
+        if let Some(permissions) = self.cached(role) {
+            return Ok(permissions);
+        }
+
+        let permissions: PermissionSet = Arc::new(
+            self.db_client
+                .get_role_permissions(role)
+                .await?
+                .into_iter()
+                .collect(),
+        );
+
+        // -- 锁中毒时跳过缓存，下次请求重新查询数据库
+        if let Ok(mut cache) = self.cache.write() {
+            cache.insert(
+                role.to_string(),
+                CachedPermissions {
+                    permissions: permissions.clone(),
+                    loaded_at: Instant::now(),
+                },
+            );
+        }
+
+        Ok(permissions)
+    }
+
+    /// 清除角色的缓存 -- 修改或删除角色后调用
+    pub fn invalidate(&self, role: &str) {
+        if let Ok(mut cache) = self.cache.write() {
+            cache.remove(role);
+        }
+    }
+
+    fn cached(&self, role: &str) -> Option<PermissionSet> {
+        let cache = self.cache.read().ok()?;
+        let cached = cache.get(role)?;
+
+        (cached.loaded_at.elapsed() < self.ttl).then(|| cached.permissions.clone())
+    }
+}
@@ -1,27 +1,83 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use axum::{middleware, Extension, Router};
+use axum::{http::Method, middleware, Extension, Router};
 use tower_http::trace::TraceLayer;
 
 use crate::{
-    handlers::{auth::auth_handler, users::users_handler},
+    handlers::{
+        admin::admin_handler, auth::auth_handler, exports::exports_handler, roles::roles_handler,
+        users::users_handler, well_known::well_known_handler,
+    },
     middleware::auth,
+    rate_limit::{RateLimitLayer, RateLimitRule},
     AppState,
 };
 
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(3600);
+const DAY: Duration = Duration::from_secs(24 * 3600);
+
 pub fn create_router(app_state: Arc<AppState>) -> Router {
+    // -- 认证接口限流：按 IP 限制请求总量，会发送邮件的接口再按邮箱限制，防止被用来轰炸他人邮箱
+    let login_rules = [RateLimitRule::per_ip(30, MINUTE)];
+    let mail_rules = [
+        RateLimitRule::per_ip(10, HOUR),
+        RateLimitRule::per_email(3, HOUR),
+    ];
+    let auth_rate_limit = RateLimitLayer::new(app_state.rate_limit_store.clone())
+        .limit(Method::POST, "/api/auth/register", mail_rules)
+        .limit(Method::POST, "/api/auth/resend-verification", mail_rules)
+        .limit(Method::POST, "/api/auth/forgot-password", mail_rules)
+        .limit(Method::POST, "/api/auth/magic-link", mail_rules)
+        .limit(Method::POST, "/api/auth/login", login_rules)
+        .limit(Method::POST, "/api/auth/mfa/verify", login_rules);
+
+    // -- 用户接口限流：放在认证中间件之后，按用户ID计数
+    let users_rate_limit = RateLimitLayer::new(app_state.rate_limit_store.clone())
+        .limit(
+            Method::PUT,
+            "/api/users/password",
+            [RateLimitRule::per_user(5, 15 * MINUTE)],
+        )
+        .limit(
+            Method::POST,
+            "/api/users/api-keys",
+            [RateLimitRule::per_user(10, HOUR)],
+        )
+        // -- 导出个人数据需要汇总大量数据并发送邮件
+        .limit(
+            Method::POST,
+            "/api/users/me/export",
+            [RateLimitRule::per_user(3, DAY)],
+        );
+
     let api_route = Router::new()
-        .nest("/auth", auth_handler())
+        .nest("/auth", auth_handler().layer(auth_rate_limit))
         // -- users 路由请求执行流程：
         // -- 1. 请求首先进入 users 路由
         // -- 2. 经过认证中间件 auth 检查请求中的 token
-        // -- 3. token 验证通过后，请求传递给具体的用户处理函数
-        .nest("/users", users_handler().layer(middleware::from_fn(auth)))
-        // -- 4. TraceLayer 记录整个请求的处理过程，包括耗时、状态等信息
+        // -- 3. 经过限流中间件检查该用户的请求频率
+        // -- 4. token 验证通过后，请求传递给具体的用户处理函数
+        .nest(
+            "/users",
+            users_handler()
+                .layer(users_rate_limit)
+                .layer(middleware::from_fn(auth)),
+        )
+        // -- 角色管理接口，同样需要先经过认证中间件
+        .nest("/roles", roles_handler().layer(middleware::from_fn(auth)))
+        // -- 用户管理接口
+        .nest("/admin", admin_handler().layer(middleware::from_fn(auth)))
+        // -- 个人数据导出的下载链接，凭下载令牌访问，不经过认证中间件
+        .nest("/exports", exports_handler())
+        // -- 5. TraceLayer 记录整个请求的处理过程，包括耗时、状态等信息
         .layer(TraceLayer::new_for_http())
-        // -- 5. Extension 中间件使处理函数能够访问应用状态（如数据库连接）
-        .layer(Extension(app_state));
+        // -- 6. Extension 中间件使处理函数能够访问应用状态（如数据库连接）
+        .layer(Extension(app_state.clone()));
 
-    // -- 创建根路由，并嵌套 API 路由
-    Router::new().nest("/api", api_route)
+    // -- 创建根路由，并嵌套 API 路由和公开的发现端点（JWKS）
+    Router::new().nest("/api", api_route).nest(
+        "/.well-known",
+        well_known_handler().layer(Extension(app_state)),
+    )
 }
@@ -1,5 +1,8 @@
+pub mod jwt_keys;
 pub mod password;
+pub mod password_policy;
 pub mod token;
+pub mod totp;
 
 use chrono::{Local, Timelike};
 use std::path::{Path, PathBuf};
@@ -0,0 +1,277 @@
+use std::{collections::HashMap, fmt, fs};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::{
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+    SigningKey, VerifyingKey,
+};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rsa::{
+    pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey},
+    traits::PublicKeyParts,
+    RsaPrivateKey, RsaPublicKey,
+};
+use sha2::{Digest, Sha256};
+
+use crate::config::JwtConfig;
+
+/// 加载 JWT 密钥时返回的错误
+pub type JwtKeyError = Box<dyn std::error::Error + Send + Sync>;
+
+/// 支持的非对称公钥
+enum PublicKey {
+    Rsa(RsaPublicKey),
+    Ed25519(VerifyingKey),
+}
+
+impl PublicKey {
+    /// 解析 PEM 格式的公钥 -- 支持 SPKI（`PUBLIC KEY`）和 PKCS#1（`RSA PUBLIC KEY`）
+    fn from_public_pem(pem: &str) -> Option<Self> {
+        if let Ok(key) = RsaPublicKey::from_public_key_pem(pem) {
+            return Some(PublicKey::Rsa(key));
+        }
+        if let Ok(key) = RsaPublicKey::from_pkcs1_pem(pem) {
+            return Some(PublicKey::Rsa(key));
+        }
+        VerifyingKey::from_public_key_pem(pem)
+            .ok()
+            .map(PublicKey::Ed25519)
+    }
+
+    /// 解析 PEM 格式的私钥并取出对应的公钥 -- 支持 PKCS#8（`PRIVATE KEY`）和 PKCS#1（`RSA PRIVATE KEY`）
+    fn from_private_pem(pem: &str) -> Option<Self> {
+        if let Ok(key) = RsaPrivateKey::from_pkcs8_pem(pem) {
+            return Some(PublicKey::Rsa(key.to_public_key()));
+        }
+        if let Ok(key) = RsaPrivateKey::from_pkcs1_pem(pem) {
+            return Some(PublicKey::Rsa(key.to_public_key()));
+        }
+        SigningKey::from_pkcs8_pem(pem)
+            .ok()
+            .map(|key| PublicKey::Ed25519(key.verifying_key()))
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        match self {
+            PublicKey::Rsa(_) => Algorithm::RS256,
+            PublicKey::Ed25519(_) => Algorithm::EdDSA,
+        }
+    }
+
+    fn parameters(&self) -> AlgorithmParameters {
+        match self {
+            PublicKey::Rsa(key) => AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+            }),
+            PublicKey::Ed25519(key) => AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(key.as_bytes()),
+            }),
+        }
+    }
+
+    /// RFC 7638 JWK 指纹 -- 作为密钥的 `kid`，同一个公钥在任何实例上得到的 `kid` 都相同
+    fn thumbprint(&self) -> String {
+        let canonical = match self.parameters() {
+            AlgorithmParameters::RSA(params) => {
+                format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, params.e, params.n)
+            }
+            AlgorithmParameters::OctetKeyPair(params) => {
+                format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, params.x)
+            }
+            _ => unreachable!("only RSA and Ed25519 keys are supported"),
+        };
+
+        URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+    }
+
+    fn to_jwk(&self) -> Jwk {
+        let key_algorithm = match self.algorithm() {
+            Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+            _ => KeyAlgorithm::RS256,
+        };
+
+        Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm),
+                key_id: Some(self.thumbprint()),
+                ..Default::default()
+            },
+            algorithm: self.parameters(),
+        }
+    }
+}
+
+/// 验证密钥及其签名算法 -- 只接受使用该算法签名的令牌，防止算法混淆攻击
+#[derive(Clone)]
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// JWT 签名与验证密钥
+///
+/// - 未配置私钥时使用 `JWT_SECRET_KEY` 进行 HS256 签名，令牌不带 `kid`，JWKS 为空
+/// - 配置了 RSA 或 Ed25519 私钥时使用 RS256 或 EdDSA 签名，令牌头部带有当前密钥的 `kid`，
+///   当前公钥和轮换期间保留的旧公钥都会发布到 JWKS
+#[derive(Clone)]
+pub struct JwtKeys {
+    algorithm: Algorithm,
+    key_id: Option<String>,
+    encoding_key: EncodingKey,
+    /// 按 `kid` 索引的验证密钥，包含当前签名密钥
+    verification_keys: HashMap<String, VerificationKey>,
+    /// 不带 `kid` 的令牌使用的验证密钥 -- 只在 HS256 模式下存在
+    secret_key: Option<VerificationKey>,
+    jwks: JwkSet,
+    issuer: String,
+    audience: String,
+}
+
+impl JwtKeys {
+    /// 根据配置加载签名密钥和验证密钥
+    pub fn from_config(config: &JwtConfig) -> Result<Self, JwtKeyError> {
+        let mut verification_keys = HashMap::new();
+        let mut jwks = JwkSet { keys: Vec::new() };
+
+        for path in &config.verification_key_files {
+            let pem = read_key_file(path)?;
+            let public_key = PublicKey::from_public_pem(&pem)
+                .ok_or_else(|| format!("{} is not an RSA or Ed25519 public key", path))?;
+            add_verification_key(&mut verification_keys, &mut jwks, &public_key)?;
+        }
+
+        let Some(path) = &config.private_key_file else {
+            let secret = config
+                .secret
+                .as_deref()
+                .ok_or("JWT_SECRET_KEY must be set when JWT_PRIVATE_KEY_FILE is not set")?;
+
+            return Ok(JwtKeys {
+                algorithm: Algorithm::HS256,
+                key_id: None,
+                encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+                verification_keys,
+                secret_key: Some(VerificationKey {
+                    algorithm: Algorithm::HS256,
+                    key: DecodingKey::from_secret(secret.as_bytes()),
+                }),
+                // -- 对称密钥不能公开，只发布轮换保留的旧公钥
+                jwks,
+                issuer: config.issuer.clone(),
+                audience: config.audience.clone(),
+            });
+        };
+
+        let pem = read_key_file(path)?;
+        let public_key = PublicKey::from_private_pem(&pem)
+            .ok_or_else(|| format!("{} is not an RSA or Ed25519 private key", path))?;
+        let encoding_key = match public_key.algorithm() {
+            Algorithm::EdDSA => EncodingKey::from_ed_pem(pem.as_bytes())?,
+            _ => EncodingKey::from_rsa_pem(pem.as_bytes())?,
+        };
+        let key_id = add_verification_key(&mut verification_keys, &mut jwks, &public_key)?;
+
+        Ok(JwtKeys {
+            algorithm: public_key.algorithm(),
+            key_id: Some(key_id),
+            encoding_key,
+            verification_keys,
+            secret_key: None,
+            jwks,
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+        })
+    }
+
+    /// 签发令牌使用的头部 -- 包含签名算法和当前密钥的 `kid`
+    pub fn header(&self) -> Header {
+        Header {
+            kid: self.key_id.clone(),
+            ..Header::new(self.algorithm)
+        }
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    pub fn audience(&self) -> &str {
+        &self.audience
+    }
+
+    /// 根据令牌头部的 `kid` 查找验证密钥，返回同时校验签名算法、`iss` 和 `aud` 的验证规则
+    ///
+    /// 未知的 `kid`，或 HS256 模式之外不带 `kid` 的令牌，返回 `None`
+    pub fn verification_key(&self, key_id: Option<&str>) -> Option<(&DecodingKey, Validation)> {
+        let key = match key_id {
+            Some(key_id) => self.verification_keys.get(key_id)?,
+            None => self.secret_key.as_ref()?,
+        };
+
+        let mut validation = Validation::new(key.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+
+        Some((&key.key, validation))
+    }
+
+    /// 公开的验证公钥，通过 `/.well-known/jwks.json` 发布
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+impl fmt::Debug for JwtKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // -- 不输出密钥内容
+        f.debug_struct("JwtKeys")
+            .field("algorithm", &self.algorithm)
+            .field("key_id", &self.key_id)
+            .field("verification_key_ids", &self.verification_keys.keys())
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .finish()
+    }
+}
+
+fn read_key_file(path: &str) -> Result<String, JwtKeyError> {
+    fs::read_to_string(path).map_err(|e| format!("failed to read key file {}: {}", path, e).into())
+}
+
+/// 添加验证公钥并发布到 JWKS，返回其 `kid`
+fn add_verification_key(
+    verification_keys: &mut HashMap<String, VerificationKey>,
+    jwks: &mut JwkSet,
+    public_key: &PublicKey,
+) -> Result<String, JwtKeyError> {
+    let jwk = public_key.to_jwk();
+    let key_id = public_key.thumbprint();
+
+    if !verification_keys.contains_key(&key_id) {
+        verification_keys.insert(
+            key_id.clone(),
+            VerificationKey {
+                algorithm: public_key.algorithm(),
+                key: DecodingKey::from_jwk(&jwk)?,
+            },
+        );
+        jwks.keys.push(jwk);
+    }
+
+    Ok(key_id)
+}
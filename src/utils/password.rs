@@ -1,14 +1,22 @@
+use std::sync::OnceLock;
+
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    Algorithm, Argon2, KeyId, Params, ParamsBuilder, Version,
 };
+use sha2::{Digest, Sha256};
+
+use crate::{config::PasswordHashConfig, error::ErrorMessage};
 
-use crate::error::ErrorMessage;
+pub const MAX_PASSWORD_LENGTH: usize = 64;
 
-const MAX_PASSWORD_LENGTH: usize = 64;
+/// 旧系统 bcrypt 哈希的前缀 -- 只用于校验，登录成功后会重新哈希为 argon2
+const BCRYPT_PREFIXES: [&str; 4] = ["$2a$", "$2b$", "$2x$", "$2y$"];
 
 /// 对密码进行哈希处理。
 ///
+/// 使用配置的 argon2id 参数；配置了 pepper 时将其作为 argon2 的密钥参与计算，
+/// 并在哈希的 `keyid` 参数中记录 pepper 的标识。
 /// 如果密码为空或超过最大允许长度，将返回错误。
 ///
 /// # 错误类型
@@ -16,7 +24,10 @@ const MAX_PASSWORD_LENGTH: usize = 64;
 /// - `EmptyPassword`: -- 密码为空时返回此错误
 /// - `ExceededMaxPasswordLength`: -- 密码超过最大允许长度时返回此错误
 /// - `HashingError`: -- 密码哈希处理失败时返回此错误
-pub fn hash(password: impl Into<String>) -> Result<String, ErrorMessage> {
+pub fn hash(
+    password: impl Into<String>,
+    config: &PasswordHashConfig,
+) -> Result<String, ErrorMessage> {
     let password = password.into();
 
     if password.is_empty() {
@@ -28,7 +39,7 @@ pub fn hash(password: impl Into<String>) -> Result<String, ErrorMessage> {
     }
 
     let salt = SaltString::generate(&mut OsRng);
-    let hashed_password = Argon2::default()
+    let hashed_password = argon2(config, argon2_params(config)?)?
         .hash_password(password.as_bytes(), &salt)
         .map_err(|_| ErrorMessage::HashingError)?
         .to_string();
@@ -42,6 +53,9 @@ pub fn hash(password: impl Into<String>) -> Result<String, ErrorMessage> {
 /// 如果原始密码与哈希密码匹配则返回 `true`，
 /// 否则返回 `false`。
 ///
+/// 支持使用任意 argon2 参数生成的哈希和旧系统的 bcrypt 哈希。
+/// 哈希使用的 pepper 与当前配置不一致时无法校验，返回 `false`。
+///
 /// 如果原始密码为空或超过最大允许长度，
 /// 此函数将返回错误。
 ///
@@ -50,7 +64,11 @@ pub fn hash(password: impl Into<String>) -> Result<String, ErrorMessage> {
 /// - `EmptyPassword`: -- 原始密码为空
 /// - `ExceededMaxPasswordLength`: -- 原始密码超过最大允许长度
 /// - `InvalidHashFormat`: -- 哈希密码格式无效
-pub fn compare(password: &str, hashed_password: &str) -> Result<bool, ErrorMessage> {
+pub fn compare(
+    password: &str,
+    hashed_password: &str,
+    config: &PasswordHashConfig,
+) -> Result<bool, ErrorMessage> {
     if password.is_empty() {
         return Err(ErrorMessage::EmptyPassword);
     }
@@ -59,11 +77,109 @@ pub fn compare(password: &str, hashed_password: &str) -> Result<bool, ErrorMessa
         return Err(ErrorMessage::ExceededMaxPasswordLength(MAX_PASSWORD_LENGTH));
     }
 
+    if is_bcrypt(hashed_password) {
+        return bcrypt::verify(password, hashed_password)
+            .map_err(|_| ErrorMessage::InvalidHashFormat);
+    }
+
     let parsed_hash =
         PasswordHash::new(hashed_password).map_err(|_| ErrorMessage::InvalidHashFormat)?;
+    let params = Params::try_from(&parsed_hash).map_err(|_| ErrorMessage::InvalidHashFormat)?;
+
+    // -- 哈希中的参数会覆盖这里的参数，只需要决定是否带上 pepper
+    let argon2 = if params.keyid().is_empty() {
+        Argon2::default()
+    } else if params.keyid() == expected_keyid(config) {
+        argon2(config, Params::default())?
+    } else {
+        tracing::error!("密码哈希使用的 pepper 与当前配置不一致，无法校验");
+        return Ok(false);
+    };
 
-    let password_matched = Argon2::default()
+    let password_matched = argon2
         .verify_password(password.as_bytes(), &parsed_hash).is_ok_and(|_| true);
 
     Ok(password_matched)
 }
+
+/// 哈希是否需要使用当前配置重新生成
+///
+/// bcrypt 哈希、argon2 算法版本或参数与配置不一致、pepper 标识与当前配置不一致时返回 `true`。
+/// 只有在密码校验通过后才能重新哈希，因此该函数只在登录成功时调用。
+pub fn needs_rehash(hashed_password: &str, config: &PasswordHashConfig) -> bool {
+    if is_bcrypt(hashed_password) {
+        return true;
+    }
+
+    let Ok(parsed_hash) = PasswordHash::new(hashed_password) else {
+        return false;
+    };
+    let Ok(params) = Params::try_from(&parsed_hash) else {
+        return false;
+    };
+
+    parsed_hash.algorithm != Algorithm::Argon2id.ident()
+        || parsed_hash.version != Some(Version::V0x13.into())
+        || params.m_cost() != config.memory_kib
+        || params.t_cost() != config.iterations
+        || params.p_cost() != config.parallelism
+        || params.keyid() != expected_keyid(config)
+}
+
+/// 与固定的哈希比较密码并丢弃结果 -- 用户不存在时调用，使响应耗时与真实的密码校验一致，
+/// 避免通过响应时间判断邮箱是否已注册。
+pub fn dummy_compare(password: &str, config: &PasswordHashConfig) {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+    let hashed_password =
+        DUMMY_HASH.get_or_init(|| hash("dummy-password", config).unwrap_or_default());
+    let _ = compare(password, hashed_password, config);
+}
+
+/// 校验 argon2 参数配置是否有效 -- 启动时调用，避免配置错误到第一次哈希时才暴露
+pub fn validate_config(config: &PasswordHashConfig) -> Result<(), ErrorMessage> {
+    argon2_params(config).map(|_| ())
+}
+
+fn is_bcrypt(hashed_password: &str) -> bool {
+    BCRYPT_PREFIXES
+        .iter()
+        .any(|prefix| hashed_password.starts_with(prefix))
+}
+
+/// 当前配置下哈希应带有的 `keyid` -- pepper SHA-256 摘要的前 8 字节，未配置 pepper 时为空
+fn expected_keyid(config: &PasswordHashConfig) -> Vec<u8> {
+    config
+        .pepper
+        .as_ref()
+        .map(|pepper| Sha256::digest(pepper.as_bytes())[..KeyId::MAX_LEN].to_vec())
+        .unwrap_or_default()
+}
+
+fn argon2_params(config: &PasswordHashConfig) -> Result<Params, ErrorMessage> {
+    let mut builder = ParamsBuilder::new();
+    builder
+        .m_cost(config.memory_kib)
+        .t_cost(config.iterations)
+        .p_cost(config.parallelism);
+
+    let keyid = expected_keyid(config);
+    if !keyid.is_empty() {
+        builder.keyid(KeyId::new(&keyid).map_err(|_| ErrorMessage::HashingError)?);
+    }
+
+    builder.build().map_err(|_| ErrorMessage::HashingError)
+}
+
+fn argon2(config: &PasswordHashConfig, params: Params) -> Result<Argon2<'_>, ErrorMessage> {
+    match &config.pepper {
+        Some(pepper) => Argon2::new_with_secret(
+            pepper.as_bytes(),
+            Algorithm::Argon2id,
+            Version::V0x13,
+            params,
+        )
+        .map_err(|_| ErrorMessage::HashingError),
+        None => Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params)),
+    }
+}
@@ -0,0 +1,136 @@
+use std::{collections::HashSet, sync::OnceLock};
+
+use validator::ValidationError;
+use zxcvbn::zxcvbn;
+
+use crate::{config::PasswordPolicyConfig, utils::password::MAX_PASSWORD_LENGTH};
+
+/// 离线常见密码列表 -- 按使用频率排序的前 10000 个，来自 zxcvbn 的密码频率表
+const COMMON_PASSWORDS: &str = include_str!("common-passwords.txt");
+
+/// 用户名、邮箱中短于该长度的片段不参与包含检查，避免误伤正常密码
+const MIN_USER_INPUT_LENGTH: usize = 3;
+
+fn common_passwords() -> &'static HashSet<&'static str> {
+    static COMMON: OnceLock<HashSet<&'static str>> = OnceLock::new();
+
+    COMMON.get_or_init(|| {
+        COMMON_PASSWORDS
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect()
+    })
+}
+
+/// 按密码策略检查密码，返回所有未满足的规则
+///
+/// # 参数
+/// - `policy` -- 密码策略
+/// - `password` -- 待检查的密码
+/// - `user_inputs` -- 用户名、邮箱等个人信息，密码中不能包含这些内容，强度评估时也会将其视为易猜测的词
+///
+/// # 返回
+/// 每条未满足的规则对应一个 `ValidationError`，全部满足时返回空列表
+pub fn check(
+    policy: &PasswordPolicyConfig,
+    password: &str,
+    user_inputs: &[&str],
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let length = password.chars().count();
+
+    if length < policy.min_length {
+        errors.push(rule_error(
+            "password_too_short",
+            format!("Password must be at least {} characters", policy.min_length),
+        ));
+    }
+
+    // -- 多字节字符按字符数可能未超限，但仍会超过密码哈希的字节上限
+    if length > policy.max_length || password.len() > MAX_PASSWORD_LENGTH {
+        errors.push(rule_error(
+            "password_too_long",
+            format!("Password must be at most {} characters", policy.max_length),
+        ));
+    }
+
+    let character_classes = [
+        (
+            policy.require_lowercase,
+            password.chars().any(char::is_lowercase),
+            "password_missing_lowercase",
+            "Password must contain a lowercase letter",
+        ),
+        (
+            policy.require_uppercase,
+            password.chars().any(char::is_uppercase),
+            "password_missing_uppercase",
+            "Password must contain an uppercase letter",
+        ),
+        (
+            policy.require_digit,
+            password.chars().any(|c| c.is_ascii_digit()),
+            "password_missing_digit",
+            "Password must contain a digit",
+        ),
+        (
+            policy.require_symbol,
+            password.chars().any(|c| !c.is_alphanumeric()),
+            "password_missing_symbol",
+            "Password must contain a symbol",
+        ),
+    ];
+
+    for (required, present, code, message) in character_classes {
+        if required && !present {
+            errors.push(rule_error(code, message.to_string()));
+        }
+    }
+
+    if contains_user_input(password, user_inputs) {
+        errors.push(rule_error(
+            "password_contains_user_info",
+            "Password must not contain your name or email".to_string(),
+        ));
+    }
+
+    if common_passwords().contains(password.to_lowercase().as_str()) {
+        errors.push(rule_error(
+            "password_too_common",
+            "Password is too common".to_string(),
+        ));
+    }
+
+    let entropy = zxcvbn(password, user_inputs);
+    if u8::from(entropy.score()) < policy.min_strength_score {
+        let message = match entropy.feedback().and_then(|feedback| feedback.warning()) {
+            Some(warning) => format!("Password is too weak: {}", warning),
+            None => "Password is too weak".to_string(),
+        };
+        errors.push(rule_error("password_too_weak", message));
+    }
+
+    errors
+}
+
+/// 密码是否包含用户名、完整邮箱或邮箱的用户名部分（不区分大小写）
+fn contains_user_input(password: &str, user_inputs: &[&str]) -> bool {
+    let password = password.to_lowercase();
+
+    user_inputs
+        .iter()
+        .map(|input| input.trim().to_lowercase())
+        .flat_map(|input| {
+            let local_part = input
+                .split_once('@')
+                .map(|(local_part, _)| local_part.to_string());
+            std::iter::once(input).chain(local_part)
+        })
+        .filter(|fragment| fragment.chars().count() >= MIN_USER_INPUT_LENGTH)
+        .any(|fragment| password.contains(&fragment))
+}
+
+fn rule_error(code: &'static str, message: String) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
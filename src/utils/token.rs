@@ -1,54 +1,158 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
 use axum::http::StatusCode;
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{decode, decode_header, encode};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-use crate::error::{ErrorMessage, HttpError};
+use crate::{
+    error::{ErrorMessage, HttpError},
+    models::User,
+    utils::jwt_keys::JwtKeys,
+};
+
+/// 令牌用途 -- 限制令牌只能用于特定流程
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    /// 完整的访问令牌
+    #[default]
+    Access,
+    /// 密码已验证、等待第二因素的临时令牌
+    MfaPending,
+    /// 登录成功但必须先修改密码的临时令牌，只能调用修改密码接口
+    PasswordChange,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenClaims {
     pub sub: String,
+    /// 签发方 -- 必须与 `JWT_ISSUER` 一致
+    pub iss: String,
+    /// 接收方 -- 必须与 `JWT_AUDIENCE` 一致
+    pub aud: String,
     pub iat: usize,
     pub exp: usize,
+    /// 令牌唯一标识 -- 退出登录时加入黑名单
+    pub jti: String,
+    /// 令牌版本 -- 与用户当前的 token_version 不一致时令牌失效
+    pub ver: i32,
+    #[serde(default)]
+    pub scope: TokenScope,
+    /// 签发时的用户角色
+    pub role: String,
+    /// 会话ID（会话族ID） -- 只有访问令牌携带，退出登录时据此吊销会话
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
 }
 
+/// 签发令牌
+///
+/// # 参数
+/// - `user` -- 令牌所属用户，令牌中记录其ID、角色和当前的令牌版本
+/// - `session_id` -- 访问令牌所属的会话族ID，临时令牌为空
+/// - `scope` -- 令牌用途
+/// - `keys` -- 签名密钥
+/// - `expires_in_minutes` -- 有效期（分钟）
 pub fn create_token(
-    user_id: &str,
-    secret: &[u8],
-    expires_in_seconds: i64,
+    user: &User,
+    session_id: Option<uuid::Uuid>,
+    scope: TokenScope,
+    keys: &JwtKeys,
+    expires_in_minutes: i64,
 ) -> Result<String, jsonwebtoken::errors::Error> {
-    if user_id.is_empty() {
-        return Err(jsonwebtoken::errors::ErrorKind::InvalidSubject.into());
-    }
-
     let now = Utc::now();
     let iat = now.timestamp() as usize;
-    let exp = (now + Duration::minutes(expires_in_seconds)).timestamp() as usize;
+    let exp = (now + Duration::minutes(expires_in_minutes)).timestamp() as usize;
     let claims = TokenClaims {
-        sub: user_id.to_string(),
+        sub: user.id.to_string(),
+        iss: keys.issuer().to_string(),
+        aud: keys.audience().to_string(),
         iat,
         exp,
+        jti: uuid::Uuid::new_v4().to_string(),
+        ver: user.token_version,
+        scope,
+        role: user.role.clone(),
+        sid: session_id.map(|session_id| session_id.to_string()),
     };
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret),
-    )
+    encode(&keys.header(), &claims, keys.encoding_key())
 }
 
-pub fn decode_token<T: Into<String>>(token: T, secret: &[u8]) -> Result<String, HttpError> {
-    let decode = decode::<TokenClaims>(
-        &token.into(),
-        &DecodingKey::from_secret(secret),
-        &Validation::new(Algorithm::HS256),
-    );
+/// 解码并验证令牌 -- 按头部的 `kid` 选择验证密钥，并校验签名算法、有效期、`iss` 和 `aud`
+pub fn decode_token<T: Into<String>>(token: T, keys: &JwtKeys) -> Result<TokenClaims, HttpError> {
+    let token = token.into();
+    let decode = decode_header(&token)
+        .ok()
+        .and_then(|header| keys.verification_key(header.kid.as_deref()))
+        .and_then(|(key, validation)| decode::<TokenClaims>(&token, key, &validation).ok());
 
     match decode {
-        Ok(token) => Ok(token.claims.sub),
-        Err(_) => Err(HttpError::new(
+        Some(token) => Ok(token.claims),
+        None => Err(HttpError::new(
             ErrorMessage::InvalidToken.to_string(),
             StatusCode::UNAUTHORIZED,
         )),
     }
 }
+
+/// 生成不透明的随机令牌 -- 32 字节随机数的十六进制编码，用于刷新令牌
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// 计算不透明令牌的 SHA-256 摘要 -- 数据库中只保存摘要，不保存原始令牌
+pub fn hash_opaque_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// 计算邮件令牌的 HMAC-SHA256 摘要 -- 使用服务端密钥，数据库泄露时无法离线穷举或伪造令牌
+///
+/// # 参数
+/// - `token` -- 邮件中发送给用户的原始令牌
+/// - `secret` -- 服务端密钥（`TOKEN_HMAC_SECRET`）
+pub fn hash_user_token(token: &str, secret: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(token.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// 恢复码字符集 -- 去掉了 0/o、1/l/i 等容易混淆的字符
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// 生成两步验证恢复码 -- 格式为 `xxxxx-xxxxx`
+pub fn generate_recovery_code() -> String {
+    let chars: String = (0..10)
+        .map(|_| {
+            let index = OsRng.next_u32() as usize % RECOVERY_CODE_ALPHABET.len();
+            RECOVERY_CODE_ALPHABET[index] as char
+        })
+        .collect();
+
+    format!("{}-{}", &chars[..5], &chars[5..])
+}
+
+/// 规范化恢复码 -- 忽略大小写、空白与连字符，哈希和比对前都需要调用
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// API 密钥前缀 -- 便于在日志和密钥扫描工具中识别泄露的密钥
+pub const API_KEY_PREFIX: &str = "axb_";
+
+/// API 密钥展示前缀的长度 -- 包含 `axb_` 和随机部分的前 8 个字符
+const API_KEY_DISPLAY_LEN: usize = API_KEY_PREFIX.len() + 8;
+
+/// 生成 API 密钥 -- 返回完整密钥和用于展示的前缀
+pub fn generate_api_key() -> (String, String) {
+    let api_key = format!("{}{}", API_KEY_PREFIX, generate_opaque_token());
+    let prefix = api_key[..API_KEY_DISPLAY_LEN].to_string();
+    (api_key, prefix)
+}
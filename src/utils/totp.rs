@@ -0,0 +1,68 @@
+use chrono::Utc;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::error::ErrorMessage;
+
+/// TOTP 时间步长（秒）
+const TOTP_STEP: u64 = 30;
+
+/// TOTP 验证码位数
+const TOTP_DIGITS: usize = 6;
+
+/// 允许的时钟偏差（步数） -- 前后各容忍一个时间步
+const TOTP_SKEW: i64 = 1;
+
+/// 生成新的 TOTP 密钥 -- 160 位随机数的 Base32 编码
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+/// 根据 Base32 密钥构建 TOTP 实例
+///
+/// # 错误类型
+///
+/// - `InvalidTotpSecret`: -- 密钥无法解码或长度不足
+fn build(secret: &str, issuer: &str, account_name: &str) -> Result<TOTP, ErrorMessage> {
+    let secret_bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|_| ErrorMessage::InvalidTotpSecret)?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP,
+        secret_bytes,
+        Some(issuer.to_string()),
+        account_name.to_string(),
+    )
+    .map_err(|_| ErrorMessage::InvalidTotpSecret)
+}
+
+/// 生成 otpauth URI -- 供身份验证器应用扫码添加账户
+pub fn otpauth_url(secret: &str, issuer: &str, account_name: &str) -> Result<String, ErrorMessage> {
+    Ok(build(secret, issuer, account_name)?.get_url())
+}
+
+/// 校验 TOTP 验证码
+///
+/// 在允许的时钟偏差范围内逐个时间步比对验证码（常量时间比较）。为防止同一验证码被重放，
+/// 只接受大于 `last_used_step` 的时间步。
+///
+/// # 返回
+/// - `Ok(Some(step))` -- 验证通过，返回匹配的时间步，调用方需要持久化
+/// - `Ok(None)` -- 验证码错误或已被使用
+pub fn verify(
+    secret: &str,
+    code: &str,
+    last_used_step: Option<i64>,
+) -> Result<Option<i64>, ErrorMessage> {
+    let totp = build(secret, "", "")?;
+    let current_step = Utc::now().timestamp() / TOTP_STEP as i64;
+
+    let matched_step = (current_step - TOTP_SKEW..=current_step + TOTP_SKEW)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| totp.check(code.trim(), *step as u64 * TOTP_STEP));
+
+    Ok(matched_step)
+}